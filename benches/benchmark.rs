@@ -2,9 +2,18 @@
 
 use criterion::{AxisScale, BenchmarkId, PlotConfiguration, Throughput};
 use criterion::{criterion_group, criterion_main, Criterion};
+use criterion::BenchmarkGroup;
+use criterion::measurement::WallTime;
+use std::simd::{LaneCount, SupportedLaneCount};
 
 use simd_base64::base64;
+#[cfg(target_arch = "x86_64")]
+use simd_base64::{avx2, avx512vbmi};
+#[cfg(target_arch = "aarch64")]
+use simd_base64::neon;
 use simd_base64::base64_simd;
+use simd_base64::swar;
+use simd_base64::Config;
 
 fn generate_base64_data(size: usize) -> Vec<u8> {
     let alphabet = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
@@ -48,6 +57,11 @@ fn bench_decode(c: &mut Criterion) {
                     base64::decode(&input, &mut Vec::new()).unwrap();
                 })
             })
+            .bench_with_input(BenchmarkId::new("swar", size), &data, |g, input| {
+                g.iter(|| {
+                    swar::decode(&input, &mut Vec::new()).unwrap();
+                })
+            })
             .bench_with_input(BenchmarkId::new("simd_8", size), &data, |g, input| {
                 g.iter(|| {
                     base64_simd::decode::<8>(&input, &mut Vec::new()).unwrap();
@@ -63,6 +77,25 @@ fn bench_decode(c: &mut Criterion) {
                     base64_simd::decode::<32>(&input, &mut Vec::new()).unwrap();
                 })
             });
+
+        #[cfg(target_arch = "x86_64")]
+        group.bench_with_input(BenchmarkId::new("avx2", size), &data, |g, input| {
+            g.iter(|| {
+                avx2::decode(&input, &mut Vec::new()).unwrap();
+            })
+        });
+        #[cfg(target_arch = "x86_64")]
+        group.bench_with_input(BenchmarkId::new("avx512vbmi", size), &data, |g, input| {
+            g.iter(|| {
+                avx512vbmi::decode(&input, &mut Vec::new()).unwrap();
+            })
+        });
+        #[cfg(target_arch = "aarch64")]
+        group.bench_with_input(BenchmarkId::new("neon", size), &data, |g, input| {
+            g.iter(|| {
+                neon::decode(&input, &mut Vec::new()).unwrap();
+            })
+        });
     }
 
     group.finish();
@@ -71,7 +104,8 @@ fn bench_decode(c: &mut Criterion) {
 fn bench_encode(c: &mut Criterion) {
     let mut group = c.benchmark_group("encode");
 
-    for size in [100, 1000, 10_000] {
+    // the largest one is mostly the main loop
+    for size in [100, 1000, 10_000, 100_000] {
         let data = generate_binary_data(size);
 
         group.throughput(Throughput::Bytes(size as u64));
@@ -86,6 +120,11 @@ fn bench_encode(c: &mut Criterion) {
                     base64::encode(&input, &mut Vec::new())
                 })
             })
+            .bench_with_input(BenchmarkId::new("swar", size), &data, |g, input| {
+                g.iter(|| {
+                    swar::encode(&input, &mut Vec::new())
+                })
+            })
             .bench_with_input(BenchmarkId::new("simd_4",  size),&data, |g, input| {
                 g.iter(|| {
                     base64_simd::encode::<4>(&input, &mut Vec::new())
@@ -105,9 +144,142 @@ fn bench_encode(c: &mut Criterion) {
                 g.iter(|| {
                     base64_simd::encode::<32>(&input, &mut Vec::new())
                 })
+            })
+            .bench_with_input(BenchmarkId::new("simd_64", size), &data, |g, input| {
+                g.iter(|| {
+                    base64_simd::encode::<64>(&input, &mut Vec::new())
+                })
+            });
+
+        #[cfg(target_arch = "x86_64")]
+        group.bench_with_input(BenchmarkId::new("avx2", size), &data, |g, input| {
+            g.iter(|| {
+                avx2::encode(&input, &mut Vec::new())
+            })
+        });
+        #[cfg(target_arch = "x86_64")]
+        group.bench_with_input(BenchmarkId::new("avx512vbmi", size), &data, |g, input| {
+            g.iter(|| {
+                avx512vbmi::encode(&input, &mut Vec::new())
+            })
+        });
+        #[cfg(target_arch = "aarch64")]
+        group.bench_with_input(BenchmarkId::new("neon", size), &data, |g, input| {
+            g.iter(|| {
+                neon::encode(&input, &mut Vec::new())
+            })
+        });
+    }
+
+    group.finish();
+}
+
+// Vectors per iteration of the main loops, for every lane width
+fn bench_unroll(c: &mut Criterion) {
+    let size = 100_000;
+
+    let mut group = c.benchmark_group("decode_unroll");
+    group.throughput(Throughput::Bytes(size as u64));
+    let data = generate_base64_data(size);
+    decode_unroll::<8>(&mut group, &data);
+    decode_unroll::<16>(&mut group, &data);
+    decode_unroll::<32>(&mut group, &data);
+    decode_unroll::<64>(&mut group, &data);
+    group.finish();
+
+    let mut group = c.benchmark_group("encode_unroll");
+    group.throughput(Throughput::Bytes(size as u64));
+    let data = generate_binary_data(size);
+    encode_unroll::<8>(&mut group, &data);
+    encode_unroll::<16>(&mut group, &data);
+    encode_unroll::<32>(&mut group, &data);
+    encode_unroll::<64>(&mut group, &data);
+    group.finish();
+}
+
+fn decode_unroll<const N: usize>(group: &mut BenchmarkGroup<WallTime>, data: &[u8])
+where
+    LaneCount<N>: SupportedLaneCount
+{
+    let config = Config::STANDARD;
+    group
+        .bench_with_input(BenchmarkId::new(format!("simd_{N}"), 1), data, |g, input| {
+            g.iter(|| base64_simd::decode_with_unroll::<N, 1>(input, &mut Vec::new(), config).unwrap())
+        })
+        .bench_with_input(BenchmarkId::new(format!("simd_{N}"), 2), data, |g, input| {
+            g.iter(|| base64_simd::decode_with_unroll::<N, 2>(input, &mut Vec::new(), config).unwrap())
+        })
+        .bench_with_input(BenchmarkId::new(format!("simd_{N}"), 4), data, |g, input| {
+            g.iter(|| base64_simd::decode_with_unroll::<N, 4>(input, &mut Vec::new(), config).unwrap())
+        });
+}
+
+fn encode_unroll<const N: usize>(group: &mut BenchmarkGroup<WallTime>, data: &[u8])
+where
+    LaneCount<N>: SupportedLaneCount
+{
+    let config = Config::STANDARD;
+    group
+        .bench_with_input(BenchmarkId::new(format!("simd_{N}"), 1), data, |g, input| {
+            g.iter(|| base64_simd::encode_with_unroll::<N, 1>(input, &mut Vec::new(), config))
+        })
+        .bench_with_input(BenchmarkId::new(format!("simd_{N}"), 2), data, |g, input| {
+            g.iter(|| base64_simd::encode_with_unroll::<N, 2>(input, &mut Vec::new(), config))
+        })
+        .bench_with_input(BenchmarkId::new(format!("simd_{N}"), 4), data, |g, input| {
+            g.iter(|| base64_simd::encode_with_unroll::<N, 4>(input, &mut Vec::new(), config))
+        });
+}
+
+// Much larger than the last-level cache, the output is reused,
+// so page faults of a new one don't hide the stores
+fn bench_non_temporal(c: &mut Criterion) {
+    let config = Config::STANDARD;
+
+    let mut group = c.benchmark_group("decode_large");
+    group.sample_size(10);
+    for size in [64 << 20, 256 << 20] {
+        let data = generate_base64_data(size);
+        let mut out = Vec::with_capacity(size);
+        group.throughput(Throughput::Bytes(size as u64));
+
+        group
+            .bench_with_input(BenchmarkId::new("simd_32", size), &data, |g, input| {
+                g.iter(|| {
+                    out.clear();
+                    base64_simd::decode_with::<32>(input, &mut out, config).unwrap();
+                })
+            })
+            .bench_with_input(BenchmarkId::new("simd_32_non_temporal", size), &data, |g, input| {
+                g.iter(|| {
+                    out.clear();
+                    base64_simd::decode_with_non_temporal::<32>(input, &mut out, config).unwrap();
+                })
             });
     }
+    group.finish();
 
+    let mut group = c.benchmark_group("encode_large");
+    group.sample_size(10);
+    for size in [64 << 20, 256 << 20] {
+        let data = generate_binary_data(size);
+        let mut out = Vec::with_capacity(size / 3 * 4 + 4);
+        group.throughput(Throughput::Bytes(size as u64));
+
+        group
+            .bench_with_input(BenchmarkId::new("simd_32", size), &data, |g, input| {
+                g.iter(|| {
+                    out.clear();
+                    base64_simd::encode_with::<32>(input, &mut out, config);
+                })
+            })
+            .bench_with_input(BenchmarkId::new("simd_32_non_temporal", size), &data, |g, input| {
+                g.iter(|| {
+                    out.clear();
+                    base64_simd::encode_with_non_temporal::<32>(input, &mut out, config);
+                })
+            });
+    }
     group.finish();
 }
 
@@ -115,6 +287,8 @@ criterion_group!(
     benches,
     bench_decode,
     bench_encode,
+    bench_unroll,
+    bench_non_temporal,
 );
 criterion_main!(
     benches
@@ -0,0 +1,64 @@
+//! Differential checks of `base64_simd` for every lane width against the scalar
+//! `base64`, shared by the fuzz targets and the replay of the corpus in `cargo test`.
+
+#![feature(portable_simd)]
+
+use std::simd::{LaneCount, SupportedLaneCount};
+
+use simd_base64::{base64, base64_simd, Config};
+
+const CONFIGS: [Config; 2] = [Config::STANDARD, Config::URL_SAFE_NO_PAD];
+
+/// Arbitrary bytes are encoded the same way by every width and decoded back.
+pub fn round_trip(data: &[u8]) {
+    round_trip_with::<4>(data);
+    round_trip_with::<8>(data);
+    round_trip_with::<16>(data);
+    round_trip_with::<32>(data);
+    round_trip_with::<64>(data);
+}
+
+/// Arbitrary bytes are accepted or rejected by every width as by the scalar
+/// decoder, with the same output or the same offset of the invalid byte.
+pub fn decode(data: &[u8]) {
+    decode_with::<4>(data);
+    decode_with::<8>(data);
+    decode_with::<16>(data);
+    decode_with::<32>(data);
+    decode_with::<64>(data);
+}
+
+fn round_trip_with<const N: usize>(data: &[u8])
+where
+    LaneCount<N>: SupportedLaneCount
+{
+    for config in CONFIGS {
+        let mut expected = Vec::new();
+        base64::encode_with(data, &mut expected, config);
+
+        let mut encoded = Vec::new();
+        base64_simd::encode_with::<N>(data, &mut encoded, config);
+        assert_eq!(encoded, expected, "encode N={N} {config:?}");
+
+        let mut decoded = Vec::new();
+        base64_simd::decode_with::<N>(&encoded, &mut decoded, config).unwrap();
+        assert_eq!(decoded, data, "round trip N={N} {config:?}");
+    }
+}
+
+fn decode_with<const N: usize>(data: &[u8])
+where
+    LaneCount<N>: SupportedLaneCount
+{
+    for config in CONFIGS {
+        // on error both leave the output as it was
+        let mut expected = b"prefix".to_vec();
+        let expected_result = base64::decode_with(data, &mut expected, config);
+
+        let mut decoded = b"prefix".to_vec();
+        let result = base64_simd::decode_with::<N>(data, &mut decoded, config);
+
+        assert_eq!(result, expected_result, "decode N={N} {config:?}");
+        assert_eq!(decoded, expected, "decode N={N} {config:?}");
+    }
+}
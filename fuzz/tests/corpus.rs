@@ -0,0 +1,30 @@
+// Replays the corpus of every fuzz target without libFuzzer: `cargo test` in fuzz/.
+// New inputs of `cargo fuzz run <target>` are saved to the same directories.
+
+use std::fs;
+use std::path::Path;
+
+fn replay(target: &str, check: fn(&[u8])) {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("corpus").join(target);
+    let entries = fs::read_dir(&dir).unwrap_or_else(|err| panic!("{}: {err}", dir.display()));
+
+    let mut count = 0;
+    for entry in entries {
+        let path = entry.unwrap().path();
+        // shown by the test harness only if the check fails
+        println!("{}", path.display());
+        check(&fs::read(&path).unwrap());
+        count += 1;
+    }
+    assert!(count > 0, "no inputs in {}", dir.display());
+}
+
+#[test]
+fn test_round_trip() {
+    replay("round_trip", simd_base64_fuzz::round_trip);
+}
+
+#[test]
+fn test_decode() {
+    replay("decode", simd_base64_fuzz::decode);
+}
@@ -0,0 +1,112 @@
+//! Base64 literals checked and converted at compile time.
+//!
+//! Both macros take a literal and optional `alphabet = standard | url_safe`
+//! and `padding = true | false` arguments, the default is standard with padding.
+//!
+//! ```
+//! use simd_base64_macros::{b64_decode, b64_encode};
+//!
+//! const HELLO: &[u8] = b64_decode!("SGVsbG8=");
+//! assert_eq!(HELLO, b"Hello");
+//!
+//! assert_eq!(b64_encode!(b"\xfb\xff", alphabet = url_safe, padding = false), "-_8");
+//! ```
+//!
+//! Invalid base64 doesn't compile, the error points at the literal:
+//!
+//! ```compile_fail
+//! let bytes = simd_base64_macros::b64_decode!("SGV$bG8=");
+//! ```
+//!
+//! ```compile_fail
+//! let bytes = simd_base64_macros::b64_decode!("SGVsbG8=", padding = false);
+//! ```
+
+use proc_macro::TokenStream;
+use proc_macro2::Span;
+use quote::quote;
+use syn::parse::{Parse, ParseStream};
+use syn::{Ident, Lit, LitBool, LitByteStr, LitStr, Token};
+
+use simd_base64::base64_simd;
+use simd_base64::{Alphabet, Config};
+
+const LANES: usize = 16;
+
+// `"literal"` or `b"literal"`, then the arguments
+struct Input {
+    value: Vec<u8>,
+    span: Span,
+    config: Config,
+}
+
+impl Parse for Input {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let literal: Lit = input.parse()?;
+        let value = match &literal {
+            Lit::Str(lit) => lit.value().into_bytes(),
+            Lit::ByteStr(lit) => lit.value(),
+            lit => return Err(syn::Error::new(lit.span(), "expected a string or byte string literal")),
+        };
+
+        let mut config = Config::STANDARD;
+        while !input.is_empty() {
+            input.parse::<Token![,]>()?;
+            if input.is_empty() {
+                break;
+            }
+
+            let name: Ident = input.parse()?;
+            input.parse::<Token![=]>()?;
+            if name == "alphabet" {
+                let alphabet: Ident = input.parse()?;
+                config.alphabet = match alphabet.to_string().as_str() {
+                    "standard" => Alphabet::Standard,
+                    "url_safe" => Alphabet::UrlSafe,
+                    _ => return Err(syn::Error::new(alphabet.span(), "expected `standard` or `url_safe`")),
+                };
+            } else if name == "padding" {
+                config.padding = input.parse::<LitBool>()?.value;
+            } else {
+                return Err(syn::Error::new(name.span(), "expected `alphabet` or `padding`"));
+            }
+        }
+
+        Ok(Input { value, span: literal.span(), config })
+    }
+}
+
+/// Decodes a base64 string literal into `&'static [u8]`.
+#[proc_macro]
+pub fn b64_decode(input: TokenStream) -> TokenStream {
+    let input = syn::parse_macro_input!(input as Input);
+
+    let mut len = input.value.len();
+    if input.config.padding {
+        len -= input.value.iter().rev().take(2).take_while(|&&byte| byte == b'=').count();
+    }
+    // Such a tail can't be decoded into a whole byte
+    if len % 4 == 1 {
+        let message = format!("invalid base64 length {len}");
+        return syn::Error::new(input.span, message).to_compile_error().into();
+    }
+
+    let mut decoded = Vec::new();
+    if let Err(err) = base64_simd::decode_with::<LANES>(&input.value, &mut decoded, input.config) {
+        // The offset is in the value of the literal, without quotes and escapes
+        return syn::Error::new(input.span, err).to_compile_error().into();
+    }
+
+    let bytes = LitByteStr::new(&decoded, Span::call_site());
+    quote!((#bytes as &'static [u8])).into()
+}
+
+/// Encodes a byte string literal into a base64 `&'static str`.
+#[proc_macro]
+pub fn b64_encode(input: TokenStream) -> TokenStream {
+    let input = syn::parse_macro_input!(input as Input);
+
+    let encoded = base64_simd::encode_to_string_with::<LANES>(&input.value, input.config);
+    let encoded = LitStr::new(&encoded, Span::call_site());
+    quote!(#encoded).into()
+}
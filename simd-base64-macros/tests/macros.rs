@@ -0,0 +1,25 @@
+use simd_base64_macros::{b64_decode, b64_encode};
+
+const HELLO: &[u8] = b64_decode!("SGVsbG8sIHdvcmxkIQ==");
+
+#[test]
+fn test_decode() {
+    assert_eq!(HELLO, b"Hello, world!");
+    assert_eq!(b64_decode!(""), b"");
+    assert_eq!(b64_decode!("SGVsbG8"), b"Hello");
+    assert_eq!(b64_decode!(b"+/8="), [0xfb, 0xff]);
+    assert_eq!(b64_decode!("-_8", alphabet = url_safe, padding = false), [0xfb, 0xff]);
+    assert_eq!(b64_decode!("+/8", alphabet = standard, padding = false,), [0xfb, 0xff]);
+}
+
+#[test]
+fn test_encode() {
+    const ENCODED: &str = b64_encode!(b"Hello, world!");
+    assert_eq!(ENCODED, "SGVsbG8sIHdvcmxkIQ==");
+
+    assert_eq!(b64_encode!(b""), "");
+    assert_eq!(b64_encode!("Hello"), "SGVsbG8=");
+    assert_eq!(b64_encode!(b"\xfb\xff"), "+/8=");
+    assert_eq!(b64_encode!(b"\xfb\xff", alphabet = url_safe), "-_8=");
+    assert_eq!(b64_encode!(b"\xfb\xff", padding = false), "+/8");
+}
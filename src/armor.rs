@@ -0,0 +1,351 @@
+//! OpenPGP ASCII armor (RFC 4880, section 6.2): PEM-like framing with
+//! `Key: Value` armor headers and a `=XXXX` CRC-24 checksum line.
+
+use std::fmt;
+
+use crate::base64_simd;
+use crate::pem;
+use crate::Config;
+
+const LANES: usize = 16;
+
+// Size of the pieces the body is decoded by, crc is computed
+// over each piece right after decoding while it is in cache
+const SEGMENT_LEN: usize = 4096;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Kind {
+    Message,
+    PublicKeyBlock,
+    PrivateKeyBlock,
+    Signature,
+    // `PGP MESSAGE, PART X/Y` or `PGP MESSAGE, PART X`
+    MessagePart { part: u32, total: Option<u32> },
+}
+
+impl Kind {
+    fn from_label(label: &str) -> Option<Kind> {
+        let kind = match label {
+            "PGP MESSAGE" => Kind::Message,
+            "PGP PUBLIC KEY BLOCK" => Kind::PublicKeyBlock,
+            "PGP PRIVATE KEY BLOCK" => Kind::PrivateKeyBlock,
+            "PGP SIGNATURE" => Kind::Signature,
+            label => {
+                let part = label.strip_prefix("PGP MESSAGE, PART ")?;
+                match part.split_once('/') {
+                    Some((part, total)) => Kind::MessagePart {
+                        part: part.parse().ok()?,
+                        total: Some(total.parse().ok()?),
+                    },
+                    None => Kind::MessagePart { part: part.parse().ok()?, total: None },
+                }
+            },
+        };
+        Some(kind)
+    }
+}
+
+impl fmt::Display for Kind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Kind::Message => write!(f, "PGP MESSAGE"),
+            Kind::PublicKeyBlock => write!(f, "PGP PUBLIC KEY BLOCK"),
+            Kind::PrivateKeyBlock => write!(f, "PGP PRIVATE KEY BLOCK"),
+            Kind::Signature => write!(f, "PGP SIGNATURE"),
+            Kind::MessagePart { part, total: Some(total) } => write!(f, "PGP MESSAGE, PART {part}/{total}"),
+            Kind::MessagePart { part, total: None } => write!(f, "PGP MESSAGE, PART {part}"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Armored {
+    pub kind: Kind,
+    pub headers: Vec<(String, String)>,
+    pub data: Vec<u8>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    Pem(pem::Error),
+    NotFound,
+    UnknownLabel { line: usize, label: String },
+    InvalidChecksum { line: usize },
+    ChecksumMismatch { line: usize, expected: u32, actual: u32 },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Pem(err) => write!(f, "{err}"),
+            Error::NotFound => write!(f, "no armored block found"),
+            Error::UnknownLabel { line, label } => write!(f, "line {line}: unknown armor label {label:?}"),
+            Error::InvalidChecksum { line } => write!(f, "line {line}: invalid checksum line"),
+            Error::ChecksumMismatch { line, expected, actual } => write!(
+                f,
+                "line {line}: checksum mismatch, expected {expected:06X}, actual {actual:06X}"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Pem(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<pem::Error> for Error {
+    fn from(err: pem::Error) -> Self {
+        Error::Pem(err)
+    }
+}
+
+/// Writes an armored block with the body wrapped at 64 columns and the checksum line.
+pub fn armor(kind: &Kind, headers: &[(&str, &str)], data: &[u8], out: &mut String) {
+    out.push_str(&format!("-----BEGIN {kind}-----\n"));
+    for (name, value) in headers {
+        out.push_str(name);
+        out.push_str(": ");
+        out.push_str(value);
+        out.push('\n');
+    }
+    out.push('\n');
+
+    // Whole lines per segment, so the line layout doesn't depend on segments
+    let segment_len = SEGMENT_LEN / pem::LINE_LEN * (pem::LINE_LEN / 4 * 3);
+    let mut crc = Crc24::new();
+    for segment in data.chunks(segment_len) {
+        crc.update(segment);
+        pem::encode_body(segment, out);
+    }
+
+    out.push('=');
+    let mut checksum = Vec::with_capacity(4);
+    base64_simd::encode::<LANES>(&crc.finish().to_be_bytes()[1..], &mut checksum);
+    out.extend(checksum.iter().map(|&b| b as char));
+    out.push('\n');
+
+    out.push_str(&format!("-----END {kind}-----\n"));
+}
+
+/// Decodes the first armored block in the input and verifies its checksum
+/// if there is one. Text before the block is ignored.
+pub fn dearmor(input: &str) -> Result<Armored, Error> {
+    let mut lines = pem::Lines::new(input);
+    let block = pem::next_block(&mut lines)?.ok_or(Error::NotFound)?;
+
+    let kind = Kind::from_label(block.label).ok_or_else(|| Error::UnknownLabel {
+        line: block.begin_line,
+        label: block.label.to_owned(),
+    })?;
+
+    // The checksum is the last line of the body
+    let trimmed = block.body.trim_end();
+    let last_line_start = trimmed.rfind('\n').map_or(0, |i| i + 1);
+    let (body, checksum) = match trimmed[last_line_start..].strip_prefix('=') {
+        Some(checksum) => (&block.body[..last_line_start], Some(checksum)),
+        None => (block.body, None),
+    };
+    let checksum_line = block.body_line + body.bytes().filter(|&b| b == b'\n').count();
+
+    let mut data = Vec::with_capacity(base64_simd::decoded_len(body.len()));
+    let actual = decode_body(&block, body, &mut data)?;
+
+    if let Some(checksum) = checksum {
+        // without padding '=' is rejected, so 4 chars are always 3 bytes
+        let mut expected = Vec::new();
+        if checksum.len() != 4
+            || base64_simd::decode_with::<LANES>(checksum.as_bytes(), &mut expected, Config::STANDARD_NO_PAD).is_err()
+        {
+            return Err(Error::InvalidChecksum { line: checksum_line });
+        }
+        let expected = u32::from_be_bytes([0, expected[0], expected[1], expected[2]]);
+
+        if expected != actual {
+            return Err(Error::ChecksumMismatch { line: checksum_line, expected, actual });
+        }
+    }
+
+    Ok(Armored {
+        kind,
+        headers: block.headers.iter().map(|&(k, v)| (k.to_owned(), v.to_owned())).collect(),
+        data,
+    })
+}
+
+// Decodes the body by segments of whole lines and returns crc of the decoded bytes
+fn decode_body(block: &pem::Block<'_>, body: &str, out: &mut Vec<u8>) -> Result<u32, Error> {
+    let mut crc = Crc24::new();
+
+    let mut decode_segment = |start: usize, end: usize, out: &mut Vec<u8>| {
+        let decoded_start = out.len();
+        base64_simd::decode_ignore_whitespace::<LANES>(&body.as_bytes()[start..end], out)
+            .map_err(|err| block.body_error(err.add_offset(start)))?;
+        crc.update(&out[decoded_start..]);
+        Ok::<_, pem::Error>(())
+    };
+
+    let mut segment_start = 0;
+    let mut segment_chars = 0;
+    let mut offset = 0;
+    for line in body.split_inclusive('\n') {
+        offset += line.len();
+        segment_chars += line.bytes().filter(|b| !b.is_ascii_whitespace()).count();
+
+        // A segment should have whole groups of 4 chars
+        if offset - segment_start >= SEGMENT_LEN && segment_chars % 4 == 0 {
+            decode_segment(segment_start, offset, out)?;
+            segment_start = offset;
+            segment_chars = 0;
+        }
+    }
+    if segment_start != body.len() {
+        decode_segment(segment_start, body.len(), out)?;
+    }
+
+    Ok(crc.finish())
+}
+
+const CRC24_INIT: u32 = 0xB704CE;
+const CRC24_POLY: u32 = 0x1864CFB;
+
+const CRC24_TABLE: [u32; 256] = crc24_table();
+
+const fn crc24_table() -> [u32; 256] {
+    let mut table = [0; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = (i as u32) << 16;
+        let mut bit = 0;
+        while bit < 8 {
+            crc <<= 1;
+            if crc & 0x1000000 != 0 {
+                crc ^= CRC24_POLY;
+            }
+            bit += 1;
+        }
+        table[i] = crc & 0xFFFFFF;
+        i += 1;
+    }
+    table
+}
+
+/// CRC-24 from RFC 4880, section 6.1.
+#[derive(Debug, Clone, Copy)]
+pub struct Crc24(u32);
+
+impl Crc24 {
+    pub fn new() -> Self {
+        Crc24(CRC24_INIT)
+    }
+
+    pub fn update(&mut self, data: &[u8]) {
+        for &byte in data {
+            let index = ((self.0 >> 16) as u8 ^ byte) as usize;
+            self.0 = ((self.0 << 8) ^ CRC24_TABLE[index]) & 0xFFFFFF;
+        }
+    }
+
+    pub fn finish(&self) -> u32 {
+        self.0
+    }
+}
+
+impl Default for Crc24 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // gpg --store --armor --compress-algo none with "Hello, world!\n"
+    const MESSAGE: &str = "\
+-----BEGIN PGP MESSAGE-----
+
+rBliBW0udHh0atULNEhlbGxvLCB3b3JsZCEK
+=xpSE
+-----END PGP MESSAGE-----
+";
+
+    #[test]
+    fn test_crc24() {
+        assert_eq!(Crc24::new().finish(), CRC24_INIT);
+
+        // bit by bit version from RFC 4880
+        let data = (0..1000).map(|i| (i * 7) as u8).collect::<Vec<_>>();
+        let mut expected = CRC24_INIT;
+        for &byte in &data {
+            expected ^= (byte as u32) << 16;
+            for _ in 0..8 {
+                expected <<= 1;
+                if expected & 0x1000000 != 0 {
+                    expected ^= CRC24_POLY;
+                }
+            }
+        }
+
+        let mut crc = Crc24::new();
+        crc.update(&data[..333]);
+        crc.update(&data[333..]);
+        assert_eq!(crc.finish(), expected & 0xFFFFFF);
+    }
+
+    #[test]
+    fn test_dearmor() {
+        let armored = dearmor(MESSAGE).unwrap();
+        assert_eq!(armored.kind, Kind::Message);
+        assert!(armored.headers.is_empty());
+        assert!(armored.data.ends_with(b"Hello, world!\n"));
+
+        let mut out = String::new();
+        armor(&armored.kind, &[], &armored.data, &mut out);
+        assert_eq!(out, MESSAGE);
+    }
+
+    #[test]
+    fn test_armor_dearmor() {
+        let data = (0..10_000).map(|i| (i * 13 + i / 256) as u8).collect::<Vec<_>>();
+        let kind = Kind::MessagePart { part: 2, total: Some(3) };
+
+        let mut out = String::new();
+        armor(&kind, &[("Version", "1"), ("Comment", "test")], &data, &mut out);
+        assert!(out.starts_with("-----BEGIN PGP MESSAGE, PART 2/3-----\nVersion: 1\nComment: test\n\n"));
+
+        let armored = dearmor(&out).unwrap();
+        assert_eq!(armored.kind, kind);
+        assert_eq!(armored.headers, [
+            ("Version".to_owned(), "1".to_owned()),
+            ("Comment".to_owned(), "test".to_owned()),
+        ]);
+        assert_eq!(armored.data, data);
+    }
+
+    #[test]
+    fn test_errors() {
+        let corrupted = MESSAGE.replace("=xpSE", "=xpSF");
+        assert!(matches!(dearmor(&corrupted), Err(Error::ChecksumMismatch { line: 4, .. })));
+
+        for checksum in ["=xp$E", "=AAA=", "====="] {
+            let corrupted = MESSAGE.replace("=xpSE", checksum);
+            assert_eq!(dearmor(&corrupted), Err(Error::InvalidChecksum { line: 4 }), "{checksum}");
+        }
+
+        let corrupted = MESSAGE.replace("MESSAGE", "NOTHING");
+        assert!(matches!(dearmor(&corrupted), Err(Error::UnknownLabel { line: 1, .. })));
+
+        let corrupted = MESSAGE.replace("atULNE", "atU$NE");
+        match dearmor(&corrupted) {
+            Err(Error::Pem(err)) => assert_eq!(err.line, 3),
+            res => panic!("unexpected {res:?}"),
+        }
+
+        assert_eq!(dearmor("text only"), Err(Error::NotFound));
+    }
+}
@@ -0,0 +1,541 @@
+//! Encoding and decoding with AVX2, or SSSE3 on CPUs without it.
+//!
+//! Bits are moved with multiplications instead of shifts and extra shuffles:
+//! `pmulhuw`/`pmullw` unpack 3 bytes into 4 sextets and `pmaddubsw`/`pmaddwd`
+//! pack them back. See Wojciech Muła, Daniel Lemire, "Faster Base64 Encoding
+//! and Decoding using AVX2 Instructions".
+//!
+//! The CPU is checked at runtime, without SSSE3 the functions fall back to
+//! [`base64_simd`] with 16 lanes.
+
+use std::arch::x86_64::*;
+
+use crate::base64_simd::{self, decoded_len, encoded_len, padded_encoded_len};
+use crate::common::{self, SEXTET_DIGITS_OFFSET, SEXTET_LOWERS_OFFSET, SEXTET_PLUS_OFFSET, SEXTET_SLASH_OFFSET, SEXTET_UPPERS_OFFSET};
+use crate::config::HI_LUT;
+use crate::{Alphabet, Config, DecodeError};
+
+const LANES: usize = 32;
+
+// A vector is 2 lanes of 16 bytes, each of them encodes 12 bytes
+const CHUNK_SIZE: usize = LANES - LANES / 4;
+
+// Decoding stores whole vectors, so there may be garbage after the output
+const EXTRA_SIZE: usize = LANES - 1;
+
+// [a, b, c] to [b, a, c, b] in every dword, so both 16-bit halves
+// have 12 bits of the input in order
+const ENCODE_SHUFFLE: [i8; 16] = [1, 0, 2, 1, 4, 3, 5, 4, 7, 6, 8, 7, 10, 9, 11, 10];
+
+// 3 low bytes of every dword in big endian order to the first 12 bytes
+const DECODE_SHUFFLE: [i8; 16] = [2, 1, 0, 6, 5, 4, 10, 9, 8, 14, 13, 12, -1, -1, -1, -1];
+
+/// Whether the CPU has SSSE3 at least, otherwise the portable code is used.
+pub fn is_available() -> bool {
+    is_x86_feature_detected!("ssse3")
+}
+
+pub fn encode(data: &[u8], out: &mut Vec<u8>) {
+    encode_with(data, out, Config::STANDARD)
+}
+
+pub fn encode_with(data: &[u8], out: &mut Vec<u8>, config: Config) {
+    if !is_available() {
+        return base64_simd::encode_with::<16>(data, out, config);
+    }
+
+    let final_size = match config.padding {
+        true => padded_encoded_len(data.len()),
+        false => encoded_len(data.len()),
+    };
+    out.reserve(final_size);
+
+    // Safety: the CPU is checked, there was allocated enough space
+    unsafe {
+        let ptr = out.as_mut_ptr().add(out.len());
+        let written = match is_x86_feature_detected!("avx2") {
+            true => encode_to_ptr(data, ptr, config.alphabet),
+            false => ssse3::encode_to_ptr(data, ptr, config.alphabet),
+        };
+        out.set_len(out.len() + written);
+    }
+
+    // padding
+    if config.padding {
+        common::pad_with_trailing_eq(data.len(), out);
+    }
+}
+
+pub fn decode(data: &[u8], out: &mut Vec<u8>) -> Result<(), DecodeError> {
+    decode_with(data, out, Config::STANDARD)
+}
+
+pub fn decode_with(data: &[u8], out: &mut Vec<u8>, config: Config) -> Result<(), DecodeError> {
+    if !is_available() {
+        return base64_simd::decode_with::<16>(data, out, config);
+    }
+
+    // without padding '=' is an invalid byte
+    let data = if config.padding { common::remove_trailing_eq(data) } else { data };
+    out.reserve(decoded_len(data.len()) + EXTRA_SIZE);
+
+    // Safety: the CPU is checked, there was allocated enough space
+    let valid = unsafe {
+        let ptr = out.as_mut_ptr().add(out.len());
+        match is_x86_feature_detected!("avx2") {
+            true => decode_to_ptr(data, ptr, config.alphabet),
+            false => ssse3::decode_to_ptr(data, ptr, config.alphabet),
+        }
+    };
+    if !valid {
+        return Err(common::find_invalid(data, config.alphabet, false));
+    }
+
+    // Safety: all decoded bytes are initialized, the garbage is after them
+    unsafe {
+        out.set_len(out.len() + decoded_len(data.len()));
+    }
+
+    Ok(())
+}
+
+// Offsets from sextets to chars, see base64_simd::encode_hot()
+fn encode_lut(alphabet: Alphabet) -> [i8; 16] {
+    base64_simd::encode_offsets(alphabet).map(|offset| offset as i8)
+}
+
+// Offsets from chars to sextets by the hash of base64_simd::decode_hot()
+fn decode_lut(alphabet: Alphabet) -> [i8; 16] {
+    let [.., char_62, char_63] = *alphabet.chars();
+    let uppers = b'A' as i8 - SEXTET_UPPERS_OFFSET as i8;
+    let lowers = b'a' as i8 - SEXTET_LOWERS_OFFSET as i8;
+    [
+        0,
+        (char_63 as i8).wrapping_sub(SEXTET_SLASH_OFFSET as i8),
+        (char_62 as i8).wrapping_sub(SEXTET_PLUS_OFFSET as i8),
+        b'0' as i8 - SEXTET_DIGITS_OFFSET as i8,
+        uppers, uppers,
+        lowers, lowers,
+        0, 0, 0, 0, 0, 0, 0, 0,
+    ]
+}
+
+// Tables of a hot loop, loaded once
+#[derive(Clone, Copy)]
+struct Luts<V> {
+    // the hash of char 63 is its high nibble plus this
+    hash_63: i8,
+    char_63: i8,
+    offsets: V,
+    lo: V,
+    hi: V,
+}
+
+// Writes exactly encoded_len(data.len()) bytes, without padding.
+//
+// Safety: the CPU has to support AVX2,
+// `out` has to be valid for writes of that many bytes.
+#[target_feature(enable = "avx2")]
+unsafe fn encode_to_ptr(data: &[u8], out: *mut u8, alphabet: Alphabet) -> usize {
+    let lut = _mm256_broadcastsi128_si256(_mm_loadu_si128(encode_lut(alphabet).as_ptr().cast()));
+    let mut ptr = out;
+    let mut input = data;
+
+    // main loop, both lanes are loaded straight from the input
+    while input.len() >= CHUNK_SIZE + 4 {
+        let bytes = load(input.as_ptr());
+        _mm256_storeu_si256(ptr.cast(), encode_hot(bytes, lut));
+        ptr = ptr.add(LANES);
+        input = &input[CHUNK_SIZE..];
+    }
+
+    // remainder, shorter than a vector, so it goes through a copy
+    for chunk in input.chunks(CHUNK_SIZE) {
+        let mut bytes = [0u8; LANES];
+        bytes[..chunk.len()].copy_from_slice(chunk);
+
+        let asciis = encode_hot(load(bytes.as_ptr()), lut);
+        let mut encoded = [0u8; LANES];
+        _mm256_storeu_si256(encoded.as_mut_ptr().cast(), asciis);
+
+        let len = encoded_len(chunk.len());
+        std::ptr::copy_nonoverlapping(encoded.as_ptr(), ptr, len);
+        ptr = ptr.add(len);
+    }
+
+    ptr.offset_from(out) as usize
+}
+
+// Writes decoded_len(data.len()) bytes and up to `EXTRA_SIZE` of garbage after them,
+// `data` is without padding. Returns whether all chars were valid.
+//
+// Safety: the CPU has to support AVX2,
+// `out` has to be valid for writes of that many bytes.
+#[target_feature(enable = "avx2")]
+unsafe fn decode_to_ptr(data: &[u8], out: *mut u8, alphabet: Alphabet) -> bool {
+    let luts = decode_luts(alphabet);
+    let mut ptr = out;
+    let mut invalid = _mm256_setzero_si256();
+
+    let mut chunks = data.chunks_exact(LANES);
+
+    // main loop
+    for chunk in &mut chunks {
+        let ascii = _mm256_loadu_si256(chunk.as_ptr().cast());
+        let (bytes, errors) = decode_hot(ascii, luts);
+        invalid = _mm256_or_si256(invalid, errors);
+
+        _mm256_storeu_si256(ptr.cast(), bytes);
+        ptr = ptr.add(decoded_len(LANES));
+    }
+
+    // remainder
+    let rest = chunks.remainder();
+    if !rest.is_empty() {
+        let mut ascii = [b'A'; LANES];
+        ascii[..rest.len()].copy_from_slice(rest);
+
+        let (bytes, errors) = decode_hot(_mm256_loadu_si256(ascii.as_ptr().cast()), luts);
+        invalid = _mm256_or_si256(invalid, errors);
+        _mm256_storeu_si256(ptr.cast(), bytes);
+    }
+
+    _mm256_testz_si256(invalid, invalid) == 1
+}
+
+#[target_feature(enable = "avx2")]
+unsafe fn decode_luts(alphabet: Alphabet) -> Luts<__m256i> {
+    let broadcast = |lut: [i8; 16]| _mm256_broadcastsi128_si256(_mm_loadu_si128(lut.as_ptr().cast()));
+    let char_63 = alphabet.chars()[63];
+    Luts {
+        hash_63: 1u8.wrapping_sub(char_63 >> 4) as i8,
+        char_63: char_63 as i8,
+        offsets: broadcast(decode_lut(alphabet)),
+        lo: broadcast(alphabet.lo_lut().map(|b| b as i8)),
+        hi: broadcast(HI_LUT.map(|b| b as i8)),
+    }
+}
+
+// 12 bytes for each lane, from `ptr` and `ptr + 12`, 28 bytes are read
+#[inline]
+#[target_feature(enable = "avx2")]
+unsafe fn load(ptr: *const u8) -> __m256i {
+    let lo = _mm_loadu_si128(ptr.cast());
+    let hi = _mm_loadu_si128(ptr.add(CHUNK_SIZE / 2).cast());
+    _mm256_set_m128i(hi, lo)
+}
+
+// 24 bytes of load() into 32 chars
+#[inline]
+#[target_feature(enable = "avx2")]
+unsafe fn encode_hot(bytes: __m256i, lut: __m256i) -> __m256i {
+    let shuffle = _mm256_broadcastsi128_si256(_mm_loadu_si128(ENCODE_SHUFFLE.as_ptr().cast()));
+    let bytes = _mm256_shuffle_epi8(bytes, shuffle);
+
+    // bbbbcccc ccdddddd aaaaaabb bbbbcccc
+    // => 00dddddd 00cccccc 00bbbbbb 00aaaaaa
+    // the multiplications are shifts of both 16-bit halves by different amounts
+    let ac = _mm256_mulhi_epu16(_mm256_and_si256(bytes, _mm256_set1_epi32(0x0fc0fc00)), _mm256_set1_epi32(0x04000040));
+    let bd = _mm256_mullo_epi16(_mm256_and_si256(bytes, _mm256_set1_epi32(0x003f03f0)), _mm256_set1_epi32(0x01000010));
+    let sextets = _mm256_or_si256(ac, bd);
+
+    // 0..=51 => 0, 52..=63 => 1..=12, then the uppercase letters are 13
+    let indices = _mm256_subs_epu8(sextets, _mm256_set1_epi8(51));
+    let uppers = _mm256_cmpgt_epi8(_mm256_set1_epi8(SEXTET_LOWERS_OFFSET as i8), sextets);
+    let indices = _mm256_or_si256(indices, _mm256_and_si256(uppers, _mm256_set1_epi8(13)));
+
+    _mm256_add_epi8(sextets, _mm256_shuffle_epi8(lut, indices))
+}
+
+// 32 chars into 24 bytes at the start of the vector, and non-zero lanes for invalid chars
+#[inline]
+#[target_feature(enable = "avx2")]
+unsafe fn decode_hot(ascii: __m256i, luts: Luts<__m256i>) -> (__m256i, __m256i) {
+    let hi_nibbles = _mm256_and_si256(_mm256_srli_epi32(ascii, 4), _mm256_set1_epi8(0x0f));
+    let lo_nibbles = _mm256_and_si256(ascii, _mm256_set1_epi8(0x0f));
+
+    // see base64_simd::validate()
+    let lo = _mm256_shuffle_epi8(luts.lo, lo_nibbles);
+    let hi = _mm256_shuffle_epi8(luts.hi, hi_nibbles);
+    let invalid = _mm256_and_si256(lo, hi);
+
+    // see base64_simd::decode_hot()
+    let eq_63 = _mm256_cmpeq_epi8(ascii, _mm256_set1_epi8(luts.char_63));
+    let hashes = _mm256_add_epi8(hi_nibbles, _mm256_and_si256(eq_63, _mm256_set1_epi8(luts.hash_63)));
+    let sextets = _mm256_sub_epi8(ascii, _mm256_shuffle_epi8(luts.offsets, hashes));
+
+    // 00aaaaaa 00bbbbbb 00cccccc 00dddddd
+    // => aaaaaabb bbbbcccc ccdddddd in the 3 low bytes of a dword
+    let pairs = _mm256_maddubs_epi16(sextets, _mm256_set1_epi32(0x01400140));
+    let dwords = _mm256_madd_epi16(pairs, _mm256_set1_epi32(0x00011000));
+
+    // 12 bytes at the start of both lanes, then the lanes next to each other
+    let shuffle = _mm256_broadcastsi128_si256(_mm_loadu_si128(DECODE_SHUFFLE.as_ptr().cast()));
+    let packed = _mm256_shuffle_epi8(dwords, shuffle);
+    let bytes = _mm256_permutevar8x32_epi32(packed, _mm256_setr_epi32(0, 1, 2, 4, 5, 6, 3, 7));
+
+    (bytes, invalid)
+}
+
+// The same with 16-byte vectors
+mod ssse3 {
+    use super::*;
+
+    const LANES: usize = 16;
+    const CHUNK_SIZE: usize = LANES - LANES / 4;
+
+    // Safety: see super::encode_to_ptr(), the CPU has to support SSSE3
+    #[target_feature(enable = "ssse3")]
+    pub(super) unsafe fn encode_to_ptr(data: &[u8], out: *mut u8, alphabet: Alphabet) -> usize {
+        let lut = _mm_loadu_si128(encode_lut(alphabet).as_ptr().cast());
+        let mut ptr = out;
+        let mut input = data;
+
+        // main loop
+        while input.len() >= LANES {
+            let bytes = _mm_loadu_si128(input.as_ptr().cast());
+            _mm_storeu_si128(ptr.cast(), encode_hot(bytes, lut));
+            ptr = ptr.add(LANES);
+            input = &input[CHUNK_SIZE..];
+        }
+
+        // remainder
+        for chunk in input.chunks(CHUNK_SIZE) {
+            let mut bytes = [0u8; LANES];
+            bytes[..chunk.len()].copy_from_slice(chunk);
+
+            let asciis = encode_hot(_mm_loadu_si128(bytes.as_ptr().cast()), lut);
+            let mut encoded = [0u8; LANES];
+            _mm_storeu_si128(encoded.as_mut_ptr().cast(), asciis);
+
+            let len = encoded_len(chunk.len());
+            std::ptr::copy_nonoverlapping(encoded.as_ptr(), ptr, len);
+            ptr = ptr.add(len);
+        }
+
+        ptr.offset_from(out) as usize
+    }
+
+    // Safety: see super::decode_to_ptr(), the CPU has to support SSSE3
+    #[target_feature(enable = "ssse3")]
+    pub(super) unsafe fn decode_to_ptr(data: &[u8], out: *mut u8, alphabet: Alphabet) -> bool {
+        let luts = decode_luts(alphabet);
+        let mut ptr = out;
+        let mut invalid = _mm_setzero_si128();
+
+        let mut chunks = data.chunks_exact(LANES);
+
+        // main loop
+        for chunk in &mut chunks {
+            let ascii = _mm_loadu_si128(chunk.as_ptr().cast());
+            let (bytes, errors) = decode_hot(ascii, luts);
+            invalid = _mm_or_si128(invalid, errors);
+
+            _mm_storeu_si128(ptr.cast(), bytes);
+            ptr = ptr.add(decoded_len(LANES));
+        }
+
+        // remainder
+        let rest = chunks.remainder();
+        if !rest.is_empty() {
+            let mut ascii = [b'A'; LANES];
+            ascii[..rest.len()].copy_from_slice(rest);
+
+            let (bytes, errors) = decode_hot(_mm_loadu_si128(ascii.as_ptr().cast()), luts);
+            invalid = _mm_or_si128(invalid, errors);
+            _mm_storeu_si128(ptr.cast(), bytes);
+        }
+
+        _mm_movemask_epi8(_mm_cmpeq_epi8(invalid, _mm_setzero_si128())) == 0xffff
+    }
+
+    #[target_feature(enable = "ssse3")]
+    pub(super) unsafe fn decode_luts(alphabet: Alphabet) -> Luts<__m128i> {
+        let load = |lut: [i8; 16]| _mm_loadu_si128(lut.as_ptr().cast());
+        let char_63 = alphabet.chars()[63];
+        Luts {
+            hash_63: 1u8.wrapping_sub(char_63 >> 4) as i8,
+            char_63: char_63 as i8,
+            offsets: load(decode_lut(alphabet)),
+            lo: load(alphabet.lo_lut().map(|b| b as i8)),
+            hi: load(HI_LUT.map(|b| b as i8)),
+        }
+    }
+
+    #[inline]
+    #[target_feature(enable = "ssse3")]
+    pub(super) unsafe fn encode_hot(bytes: __m128i, lut: __m128i) -> __m128i {
+        let bytes = _mm_shuffle_epi8(bytes, _mm_loadu_si128(ENCODE_SHUFFLE.as_ptr().cast()));
+
+        let ac = _mm_mulhi_epu16(_mm_and_si128(bytes, _mm_set1_epi32(0x0fc0fc00)), _mm_set1_epi32(0x04000040));
+        let bd = _mm_mullo_epi16(_mm_and_si128(bytes, _mm_set1_epi32(0x003f03f0)), _mm_set1_epi32(0x01000010));
+        let sextets = _mm_or_si128(ac, bd);
+
+        let indices = _mm_subs_epu8(sextets, _mm_set1_epi8(51));
+        let uppers = _mm_cmpgt_epi8(_mm_set1_epi8(SEXTET_LOWERS_OFFSET as i8), sextets);
+        let indices = _mm_or_si128(indices, _mm_and_si128(uppers, _mm_set1_epi8(13)));
+
+        _mm_add_epi8(sextets, _mm_shuffle_epi8(lut, indices))
+    }
+
+    #[inline]
+    #[target_feature(enable = "ssse3")]
+    pub(super) unsafe fn decode_hot(ascii: __m128i, luts: Luts<__m128i>) -> (__m128i, __m128i) {
+        let hi_nibbles = _mm_and_si128(_mm_srli_epi32(ascii, 4), _mm_set1_epi8(0x0f));
+        let lo_nibbles = _mm_and_si128(ascii, _mm_set1_epi8(0x0f));
+
+        let lo = _mm_shuffle_epi8(luts.lo, lo_nibbles);
+        let hi = _mm_shuffle_epi8(luts.hi, hi_nibbles);
+        let invalid = _mm_and_si128(lo, hi);
+
+        let eq_63 = _mm_cmpeq_epi8(ascii, _mm_set1_epi8(luts.char_63));
+        let hashes = _mm_add_epi8(hi_nibbles, _mm_and_si128(eq_63, _mm_set1_epi8(luts.hash_63)));
+        let sextets = _mm_sub_epi8(ascii, _mm_shuffle_epi8(luts.offsets, hashes));
+
+        let pairs = _mm_maddubs_epi16(sextets, _mm_set1_epi32(0x01400140));
+        let dwords = _mm_madd_epi16(pairs, _mm_set1_epi32(0x00011000));
+
+        (_mm_shuffle_epi8(dwords, _mm_loadu_si128(DECODE_SHUFFLE.as_ptr().cast())), invalid)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::simd::Simd;
+
+    use super::*;
+    use crate::base64;
+    use crate::common::{check_round_trip, test_data, test_vectors};
+
+    const ALPHABETS: [Alphabet; 2] = [Alphabet::Standard, Alphabet::UrlSafe];
+
+    // chars of the alphabet and any bytes
+    fn asciis<const N: usize>(alphabet: Alphabet) -> impl Iterator<Item = [u8; N]> {
+        let chars = test_vectors().map(move |bytes: [u8; N]| bytes.map(|b| alphabet.chars()[b as usize % 64]));
+        chars.chain(test_vectors())
+    }
+
+    #[test]
+    fn test_encode_hot() {
+        if !is_x86_feature_detected!("avx2") {
+            eprintln!("AVX2 is not available, skipped");
+            return;
+        }
+
+        for alphabet in ALPHABETS {
+            for bytes in test_vectors::<32>() {
+                let expected = base64_simd::encode_hot(Simd::from(bytes), alphabet);
+                let expected_16 = base64_simd::encode_hot(Simd::from(*bytes.first_chunk::<16>().unwrap()), alphabet);
+
+                // Safety: the CPU is checked above
+                let (asciis, asciis_16) = unsafe {
+                    let lut = encode_lut(alphabet);
+                    let lut_16 = _mm_loadu_si128(lut.as_ptr().cast());
+                    (
+                        encode_hot(load(bytes.as_ptr()), _mm256_broadcastsi128_si256(lut_16)),
+                        ssse3::encode_hot(_mm_loadu_si128(bytes.as_ptr().cast()), lut_16),
+                    )
+                };
+                assert_eq!(Simd::<u8, 32>::from(asciis), expected);
+                assert_eq!(Simd::<u8, 16>::from(asciis_16), expected_16);
+            }
+        }
+    }
+
+    #[test]
+    fn test_decode_hot() {
+        if !is_x86_feature_detected!("avx2") {
+            eprintln!("AVX2 is not available, skipped");
+            return;
+        }
+
+        for alphabet in ALPHABETS {
+            for ascii in asciis::<32>(alphabet) {
+                let (expected, ok) = base64_simd::decode_hot(Simd::from(ascii), alphabet);
+                // Safety: the CPU is checked above
+                let (bytes, invalid) = unsafe { decode_hot(Simd::from(ascii).into(), decode_luts(alphabet)) };
+                assert_eq!(Simd::<u8, 32>::from(invalid) == Simd::splat(0), ok);
+                if ok {
+                    assert_eq!(Simd::<u8, 32>::from(bytes).as_array()[..24], expected.as_array()[..24]);
+                }
+            }
+
+            for ascii in asciis::<16>(alphabet) {
+                let (expected, ok) = base64_simd::decode_hot(Simd::from(ascii), alphabet);
+                // Safety: the CPU is checked above
+                let (bytes, invalid) = unsafe { ssse3::decode_hot(Simd::from(ascii).into(), ssse3::decode_luts(alphabet)) };
+                assert_eq!(Simd::<u8, 16>::from(invalid) == Simd::splat(0), ok);
+                if ok {
+                    assert_eq!(Simd::<u8, 16>::from(bytes).as_array()[..12], expected.as_array()[..12]);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_encode_decode() {
+        // load() reads 28 bytes for the 24 of a vector, so the main loop
+        // stops below 28 and the remainder takes up to 27 bytes
+        for len in (0..=60).chain([1000, 10_000]) {
+            let data = test_data(len);
+            for config in [Config::STANDARD, Config::URL_SAFE_NO_PAD] {
+                check_round_trip(&data, config, encode_with, decode_with);
+
+                if !is_available() {
+                    continue;
+                }
+                // the SSSE3 loops, even if the CPU has AVX2
+                let mut expected = Vec::new();
+                base64::encode_with(&data, &mut expected, Config { padding: false, ..config });
+                // Safety: the CPU is checked above, the output has room for the garbage
+                unsafe {
+                    let mut encoded = vec![0; expected.len()];
+                    let written = ssse3::encode_to_ptr(&data, encoded.as_mut_ptr(), config.alphabet);
+                    assert_eq!(encoded[..written], expected);
+
+                    let mut decoded = vec![0; len + EXTRA_SIZE];
+                    assert!(ssse3::decode_to_ptr(&encoded, decoded.as_mut_ptr(), config.alphabet));
+                    assert_eq!(decoded[..len], data);
+                }
+            }
+        }
+    }
+
+    // Chars 62 and 63 are found by their own compare and hash, these bytes
+    // share their high or low nibble and must not pass for them
+    #[test]
+    fn test_errors() {
+        let cases = [
+            (Config::STANDARD_NO_PAD, [b'-', b'_', b'?', b';', b'[', b'{', 0x0f, 0x7f, 0xab, 0xaf]),
+            (Config::URL_SAFE_NO_PAD, [b'+', b'/', b'?', b'=', b']', b'}', 0x0d, 0x7f, 0xad, 0xdf]),
+        ];
+
+        for (config, bytes) in cases {
+            // 4 vectors of the main loop and 6 chars of the remainder
+            let mut encoded = Vec::new();
+            base64::encode_with(&test_data(100), &mut encoded, config);
+
+            for byte in bytes {
+                // both 128-bit lanes, the next vector and the remainder
+                for offset in [5, 20, 40, 130] {
+                    let mut invalid = encoded.clone();
+                    invalid[offset] = byte;
+
+                    let mut out = vec![1];
+                    assert_eq!(
+                        decode_with(&invalid, &mut out, config),
+                        Err(DecodeError::InvalidByte { offset, byte })
+                    );
+                    assert_eq!(out, [1]);
+
+                    if is_available() {
+                        let mut decoded = vec![0; 100 + EXTRA_SIZE];
+                        // Safety: the CPU is checked, the output has room for the garbage
+                        let valid = unsafe { ssse3::decode_to_ptr(&invalid, decoded.as_mut_ptr(), config.alphabet) };
+                        assert!(!valid, "{byte:#x} at {offset}");
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,342 @@
+//! Encoding and decoding with AVX-512 VBMI, e.g. on Ice Lake and Zen 4.
+//!
+//! `vpermb` looks up in a whole 64-byte register, so the alphabet is a single
+//! table, and `vpmultishiftqb` extracts the sextets of 3 bytes at once.
+//! See Wojciech Muła, "Base64 encoding and decoding with AVX512VBMI".
+//!
+//! The CPU is checked at runtime, without the instructions the functions
+//! fall back to [`base64_simd`] with 64 lanes.
+
+use std::arch::x86_64::*;
+
+use crate::base64_simd::{self, decoded_len, encoded_len, padded_encoded_len};
+use crate::common;
+use crate::{Alphabet, Config, DecodeError};
+
+const LANES: usize = 64;
+
+// Input of one vector, the output of encoding is the whole vector
+const CHUNK_SIZE: usize = LANES - LANES / 4;
+
+// Every 3 bytes [a, b, c] to [b, a, c, b], so both 16-bit halves of a dword
+// have 12 bits of the input in order
+const ENCODE_SHUFFLE: [u8; LANES] = {
+    let mut indices = [0; LANES];
+    let mut i = 0;
+    while i < LANES / 4 {
+        let [a, b, c] = [3 * i as u8, 3 * i as u8 + 1, 3 * i as u8 + 2];
+        indices[4 * i] = b;
+        indices[4 * i + 1] = a;
+        indices[4 * i + 2] = c;
+        indices[4 * i + 3] = b;
+        i += 1;
+    }
+    indices
+};
+
+// Bit offsets of the 4 sextets of both dwords of a qword
+const ENCODE_SHIFTS: [u8; 8] = [10, 4, 22, 16, 32 + 10, 32 + 4, 32 + 22, 32 + 16];
+
+// 3 low bytes of every dword in big endian order to the first 48 bytes
+const DECODE_SHUFFLE: [u8; LANES] = {
+    let mut indices = [0; LANES];
+    let mut i = 0;
+    while i < LANES / 4 {
+        indices[3 * i] = 4 * i as u8 + 2;
+        indices[3 * i + 1] = 4 * i as u8 + 1;
+        indices[3 * i + 2] = 4 * i as u8;
+        i += 1;
+    }
+    indices
+};
+
+/// Whether the CPU has the instructions, otherwise the portable code is used.
+pub fn is_available() -> bool {
+    is_x86_feature_detected!("avx512bw") && is_x86_feature_detected!("avx512vbmi")
+}
+
+pub fn encode(data: &[u8], out: &mut Vec<u8>) {
+    encode_with(data, out, Config::STANDARD)
+}
+
+pub fn encode_with(data: &[u8], out: &mut Vec<u8>, config: Config) {
+    if !is_available() {
+        return base64_simd::encode_with::<LANES>(data, out, config);
+    }
+
+    let final_size = match config.padding {
+        true => padded_encoded_len(data.len()),
+        false => encoded_len(data.len()),
+    };
+    out.reserve(final_size);
+
+    // Safety: the CPU is checked above, there was allocated enough space
+    unsafe {
+        let written = encode_to_ptr(data, out.as_mut_ptr().add(out.len()), config.alphabet);
+        out.set_len(out.len() + written);
+    }
+
+    // padding
+    if config.padding {
+        common::pad_with_trailing_eq(data.len(), out);
+    }
+}
+
+pub fn decode(data: &[u8], out: &mut Vec<u8>) -> Result<(), DecodeError> {
+    decode_with(data, out, Config::STANDARD)
+}
+
+pub fn decode_with(data: &[u8], out: &mut Vec<u8>, config: Config) -> Result<(), DecodeError> {
+    if !is_available() {
+        return base64_simd::decode_with::<LANES>(data, out, config);
+    }
+
+    // without padding '=' is an invalid byte
+    let data = if config.padding { common::remove_trailing_eq(data) } else { data };
+    out.reserve(decoded_len(data.len()));
+
+    // Safety: the CPU is checked above, there was allocated enough space
+    unsafe {
+        let written = decode_to_ptr(data, out.as_mut_ptr().add(out.len()), config.alphabet)?;
+        out.set_len(out.len() + written);
+    }
+
+    Ok(())
+}
+
+// Writes exactly encoded_len(data.len()) bytes, without padding.
+//
+// Safety: the CPU has to support AVX-512 VBMI,
+// `out` has to be valid for writes of that many bytes.
+#[target_feature(enable = "avx512bw,avx512vbmi")]
+unsafe fn encode_to_ptr(data: &[u8], out: *mut u8, alphabet: Alphabet) -> usize {
+    let lut = _mm512_loadu_si512(alphabet.chars().as_ptr().cast());
+    let mut ptr = out;
+    let mut input = data;
+
+    // main loop, only the first `CHUNK_SIZE` bytes of a loaded vector are encoded
+    while input.len() >= LANES {
+        let bytes = _mm512_loadu_si512(input.as_ptr().cast());
+        _mm512_storeu_si512(ptr.cast(), encode_hot(bytes, lut));
+        ptr = ptr.add(LANES);
+        input = &input[CHUNK_SIZE..];
+    }
+
+    // remainder, masked loads and stores instead of copies
+    for chunk in input.chunks(CHUNK_SIZE) {
+        let bytes = _mm512_maskz_loadu_epi8(mask(chunk.len()), chunk.as_ptr().cast());
+        let len = encoded_len(chunk.len());
+        _mm512_mask_storeu_epi8(ptr.cast(), mask(len), encode_hot(bytes, lut));
+        ptr = ptr.add(len);
+    }
+
+    ptr.offset_from(out) as usize
+}
+
+// Writes exactly decoded_len(data.len()) bytes, `data` is without padding.
+//
+// Safety: the CPU has to support AVX-512 VBMI,
+// `out` has to be valid for writes of that many bytes.
+#[target_feature(enable = "avx512bw,avx512vbmi")]
+unsafe fn decode_to_ptr(data: &[u8], out: *mut u8, alphabet: Alphabet) -> Result<usize, DecodeError> {
+    let lut = alphabet.decode_lut();
+    let lut_lo = _mm512_loadu_si512(lut[..LANES].as_ptr().cast());
+    let lut_hi = _mm512_loadu_si512(lut[LANES..].as_ptr().cast());
+
+    let mut ptr = out;
+    let mut chunks = data.chunks_exact(LANES);
+
+    // main loop
+    for (i, chunk) in (&mut chunks).enumerate() {
+        let ascii = _mm512_loadu_si512(chunk.as_ptr().cast());
+        let (bytes, invalid) = decode_hot(ascii, lut_lo, lut_hi);
+        if invalid != 0 {
+            return Err(invalid_byte(data, i * LANES, invalid));
+        }
+
+        let len = decoded_len(LANES);
+        _mm512_mask_storeu_epi8(ptr.cast(), mask(len), bytes);
+        ptr = ptr.add(len);
+    }
+
+    // remainder, the rest of the vector is filled with 'A'
+    let rest = chunks.remainder();
+    if !rest.is_empty() {
+        let ascii = _mm512_mask_loadu_epi8(_mm512_set1_epi8(b'A' as i8), mask(rest.len()), rest.as_ptr().cast());
+        let (bytes, invalid) = decode_hot(ascii, lut_lo, lut_hi);
+        if invalid != 0 {
+            return Err(invalid_byte(data, data.len() - rest.len(), invalid));
+        }
+
+        let len = decoded_len(rest.len());
+        _mm512_mask_storeu_epi8(ptr.cast(), mask(len), bytes);
+        ptr = ptr.add(len);
+    }
+
+    Ok(ptr.offset_from(out) as usize)
+}
+
+// 48 bytes of the first 3/4 of `bytes` into 64 chars
+#[inline]
+#[target_feature(enable = "avx512bw,avx512vbmi")]
+unsafe fn encode_hot(bytes: __m512i, lut: __m512i) -> __m512i {
+    let shuffled = _mm512_permutexvar_epi8(load(&ENCODE_SHUFFLE), bytes);
+
+    // 8 bits at every offset, the 2 high bits of every byte are garbage
+    let shifts = _mm512_set1_epi64(i64::from_le_bytes(ENCODE_SHIFTS));
+    let sextets = _mm512_multishift_epi64_epi8(shifts, shuffled);
+
+    // vpermb uses only the 6 low bits of indices
+    _mm512_permutexvar_epi8(sextets, lut)
+}
+
+// 64 chars into 48 bytes at the start of the vector, and the mask of invalid chars
+#[inline]
+#[target_feature(enable = "avx512bw,avx512vbmi")]
+unsafe fn decode_hot(ascii: __m512i, lut_lo: __m512i, lut_hi: __m512i) -> (__m512i, u64) {
+    // vpermi2b looks up in both tables by the 7 low bits,
+    // chars out of the alphabet have bit 7 set, non-ASCII chars have it already
+    let sextets = _mm512_permutex2var_epi8(lut_lo, ascii, lut_hi);
+    let invalid = _mm512_movepi8_mask(_mm512_or_si512(sextets, ascii));
+
+    // 00aaaaaa 00bbbbbb 00cccccc 00dddddd
+    // => aaaaaabb bbbbcccc ccdddddd in the 3 low bytes of a dword
+    let pairs = _mm512_maddubs_epi16(sextets, _mm512_set1_epi32(0x01400140));
+    let dwords = _mm512_madd_epi16(pairs, _mm512_set1_epi32(0x00011000));
+
+    (_mm512_permutexvar_epi8(load(&DECODE_SHUFFLE), dwords), invalid)
+}
+
+#[inline]
+#[target_feature(enable = "avx512bw,avx512vbmi")]
+unsafe fn load(array: &[u8; LANES]) -> __m512i {
+    _mm512_loadu_si512(array.as_ptr().cast())
+}
+
+// Mask of the first `len` lanes
+fn mask(len: usize) -> u64 {
+    match len {
+        LANES => u64::MAX,
+        len => (1 << len) - 1,
+    }
+}
+
+#[cold]
+fn invalid_byte(data: &[u8], start: usize, invalid: u64) -> DecodeError {
+    let offset = start + invalid.trailing_zeros() as usize;
+    DecodeError::InvalidByte { offset, byte: data[offset] }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::simd::Simd;
+
+    use super::*;
+    use crate::base64;
+    use crate::common::{check_round_trip, test_data, test_vectors};
+
+    // Every test passes on CPUs without the instructions
+    fn skip() -> bool {
+        if !is_available() {
+            eprintln!("AVX-512 VBMI is not available, skipped");
+        }
+        !is_available()
+    }
+
+    #[test]
+    fn test_encode_hot() {
+        if skip() {
+            return;
+        }
+
+        for alphabet in [Alphabet::Standard, Alphabet::UrlSafe] {
+            for bytes in test_vectors::<LANES>() {
+                let expected = base64_simd::encode_hot(Simd::from(bytes), alphabet);
+                // Safety: the CPU is checked above
+                let asciis = unsafe {
+                    let lut = _mm512_loadu_si512(alphabet.chars().as_ptr().cast());
+                    encode_hot(Simd::from(bytes).into(), lut)
+                };
+                assert_eq!(Simd::<u8, LANES>::from(asciis), expected);
+            }
+        }
+    }
+
+    #[test]
+    fn test_decode_hot() {
+        if skip() {
+            return;
+        }
+
+        for alphabet in [Alphabet::Standard, Alphabet::UrlSafe] {
+            let lut = alphabet.decode_lut();
+            // chars of the alphabet and any bytes
+            let asciis = test_vectors::<LANES>().map(|bytes| bytes.map(|b| alphabet.chars()[b as usize % 64]));
+            for ascii in asciis.chain(test_vectors()) {
+                let (expected, ok) = base64_simd::decode_hot(Simd::from(ascii), alphabet);
+                // Safety: the CPU is checked above
+                let (bytes, invalid) = unsafe {
+                    let lut_lo = _mm512_loadu_si512(lut[..LANES].as_ptr().cast());
+                    let lut_hi = _mm512_loadu_si512(lut[LANES..].as_ptr().cast());
+                    decode_hot(Simd::from(ascii).into(), lut_lo, lut_hi)
+                };
+                assert_eq!(invalid == 0, ok);
+                if ok {
+                    let bytes = Simd::<u8, LANES>::from(bytes);
+                    assert_eq!(bytes.as_array()[..48], expected.as_array()[..48]);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_encode_decode() {
+        if skip() {
+            return;
+        }
+
+        // around the 48 bytes encoded from a load of 64, and the masked remainder
+        for len in [0, 1, 2, 3, 47, 48, 49, 63, 64, 65, 95, 96, 97, 111, 112, 113, 1000, 10_000] {
+            for config in [Config::STANDARD, Config::URL_SAFE_NO_PAD] {
+                check_round_trip(&test_data(len), config, encode_with, decode_with);
+            }
+        }
+    }
+
+    #[test]
+    fn test_errors() {
+        if skip() {
+            return;
+        }
+
+        // 4 vectors of the main loop and 11 chars of the remainder
+        let mut encoded = Vec::new();
+        base64::encode(&test_data(200), &mut encoded);
+        let len = common::remove_trailing_eq(&encoded).len();
+
+        // vpermi2b sees only the 7 low bits, these bytes look up chars of the
+        // alphabet and only their bit 7 makes them invalid
+        for byte in [0x80 | b'A', 0x80 | b'z', 0x80 | b'+', 0xff] {
+            // the first and the last lane of vectors, and the masked remainder
+            for offset in [0, 63, 64, 255, 256, len - 1] {
+                let mut invalid = encoded.clone();
+                invalid[offset] = byte;
+                let mut out = vec![1];
+                assert_eq!(decode(&invalid, &mut out), Err(DecodeError::InvalidByte { offset, byte }));
+                assert_eq!(out, [1]);
+            }
+        }
+
+        // the lowest bit of the mask is the first invalid byte
+        let mut invalid = encoded.clone();
+        invalid[70] = b'*';
+        invalid[66] = b'$';
+        invalid[260] = b'*';
+        assert_eq!(decode(&invalid, &mut Vec::new()), Err(DecodeError::InvalidByte { offset: 66, byte: b'$' }));
+
+        assert_eq!(
+            decode(b"SGVs=G8=", &mut Vec::new()),
+            Err(DecodeError::InvalidByte { offset: 4, byte: b'=' })
+        );
+    }
+}
@@ -1,3 +1,4 @@
+use crate::{Alphabet, Config, DecodeError};
 use crate::common;
 use crate::common::{
     SEXTET_UPPERS_OFFSET,
@@ -8,7 +9,7 @@ use crate::common::{
 };
 
 
-fn decoded_len(encoded_len: usize) -> usize {
+pub const fn decoded_len(encoded_len: usize) -> usize {
     let padding = match encoded_len % 4 {
         1 | 2 => 1,
         3 => 2,
@@ -18,23 +19,26 @@ fn decoded_len(encoded_len: usize) -> usize {
     encoded_len / 4 * 3 + padding
 }
 
-pub fn decode(data: &[u8], out: &mut Vec<u8>) -> Result<(), String> {
-    let data = common::remove_trailing_eq(data);
+pub fn decode(data: &[u8], out: &mut Vec<u8>) -> Result<(), DecodeError> {
+    decode_with(data, out, Config::STANDARD)
+}
+
+pub fn decode_with(data: &[u8], out: &mut Vec<u8>, config: Config) -> Result<(), DecodeError> {
+    // without padding '=' is an invalid byte
+    let data = if config.padding { common::remove_trailing_eq(data) } else { data };
 
     let final_size = decoded_len(data.len());
     out.reserve(final_size);
+    // on error `out` is left as it was
+    let start = out.len();
 
-    for chunk in data.chunks(4) {
+    for (i, chunk) in data.chunks(4).enumerate() {
         let mut bytes: u32 = 0;
 
-        for byte in chunk {
-            let sextet = match byte {
-                b'A'..=b'Z' => byte - b'A' + SEXTET_UPPERS_OFFSET,
-                b'a'..=b'z' => byte - b'a' + SEXTET_LOWERS_OFFSET,
-                b'0'..=b'9' => byte - b'0' + SEXTET_DIGITS_OFFSET,
-                b'+' => SEXTET_PLUS_OFFSET,
-                b'/' => SEXTET_SLASH_OFFSET,
-                _ => return Err(format!("{data:?} is not base64 because of char {byte}")),
+        for (j, &byte) in chunk.iter().enumerate() {
+            let Some(sextet) = ascii_to_sextet(byte, config.alphabet) else {
+                out.truncate(start);
+                return Err(DecodeError::InvalidByte { offset: i * 4 + j, byte });
             };
 
             bytes <<= 6;
@@ -52,7 +56,7 @@ pub fn decode(data: &[u8], out: &mut Vec<u8>) -> Result<(), String> {
     Ok(())
 }
 
-fn encoded_len(decoded_len: usize) -> usize {
+pub const fn encoded_len(decoded_len: usize) -> usize {
     let padding = match decoded_len % 3 {
         1 => 2,
         2 => 3,
@@ -61,14 +65,31 @@ fn encoded_len(decoded_len: usize) -> usize {
     decoded_len / 3 * 4 + padding
 }
 
-fn sextet_to_ascii(sextet: u8) -> u8 {
-    let alphabet = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
-    alphabet[sextet as usize]
+const fn ascii_to_sextet(byte: u8, alphabet: Alphabet) -> Option<u8> {
+    let chars = alphabet.chars();
+    let sextet = match byte {
+        b'A'..=b'Z' => byte - b'A' + SEXTET_UPPERS_OFFSET,
+        b'a'..=b'z' => byte - b'a' + SEXTET_LOWERS_OFFSET,
+        b'0'..=b'9' => byte - b'0' + SEXTET_DIGITS_OFFSET,
+        _ if byte == chars[62] => SEXTET_PLUS_OFFSET,
+        _ if byte == chars[63] => SEXTET_SLASH_OFFSET,
+        _ => return None,
+    };
+    Some(sextet)
+}
+
+const fn sextet_to_ascii(sextet: u8, alphabet: Alphabet) -> u8 {
+    alphabet.chars()[sextet as usize]
 }
 
 pub fn encode(data: &[u8], out: &mut Vec<u8>) {
+    encode_with(data, out, Config::STANDARD)
+}
+
+pub fn encode_with(data: &[u8], out: &mut Vec<u8>, config: Config) {
     let final_size = encoded_len(data.len());
     out.reserve(final_size + 2 /* padding */);
+    let alphabet = config.alphabet;
 
     let mut chunks = data.chunks_exact(3);
     
@@ -76,10 +97,10 @@ pub fn encode(data: &[u8], out: &mut Vec<u8>) {
     for chunk in &mut chunks {
         let bytes: u32 = ((chunk[0] as u32) << 16) | ((chunk[1] as u32) << 8) | (chunk[2] as u32);
 
-        out.push(sextet_to_ascii((bytes >> 18) as u8 & 0b111111));
-        out.push(sextet_to_ascii((bytes >> 12) as u8 & 0b111111));
-        out.push(sextet_to_ascii((bytes >> 6) as u8 & 0b111111));
-        out.push(sextet_to_ascii((bytes >> 0) as u8 & 0b111111));
+        out.push(sextet_to_ascii((bytes >> 18) as u8 & 0b111111, alphabet));
+        out.push(sextet_to_ascii((bytes >> 12) as u8 & 0b111111, alphabet));
+        out.push(sextet_to_ascii((bytes >> 6) as u8 & 0b111111, alphabet));
+        out.push(sextet_to_ascii((bytes >> 0) as u8 & 0b111111, alphabet));
     }
 
     // remainder + padding
@@ -87,22 +108,143 @@ pub fn encode(data: &[u8], out: &mut Vec<u8>) {
     match rem.len() {
         1 => {
             let bytes = (rem[0] as u32) << 16;
-            out.push(sextet_to_ascii((bytes >> 18) as u8 & 0b111111));
-            out.push(sextet_to_ascii((bytes >> 12) as u8 & 0b111111));
-            out.push(b'=');
-            out.push(b'=');
+            out.push(sextet_to_ascii((bytes >> 18) as u8 & 0b111111, alphabet));
+            out.push(sextet_to_ascii((bytes >> 12) as u8 & 0b111111, alphabet));
+            if config.padding {
+                out.push(b'=');
+                out.push(b'=');
+            }
         },
         2 => {
             let bytes = ((rem[0] as u32) << 16) | ((rem[1] as u32) << 8);
-            out.push(sextet_to_ascii((bytes >> 18) as u8 & 0b111111));
-            out.push(sextet_to_ascii((bytes >> 12) as u8 & 0b111111));
-            out.push(sextet_to_ascii((bytes >> 6) as u8 & 0b111111));
-            out.push(b'=');
+            out.push(sextet_to_ascii((bytes >> 18) as u8 & 0b111111, alphabet));
+            out.push(sextet_to_ascii((bytes >> 12) as u8 & 0b111111, alphabet));
+            out.push(sextet_to_ascii((bytes >> 6) as u8 & 0b111111, alphabet));
+            if config.padding {
+                out.push(b'=');
+            }
         },
         _ => {},
     }
 }
 
+pub fn encode_to_string(data: &[u8]) -> String {
+    let mut out = String::new();
+    encode_append_string(data, &mut out);
+    out
+}
+
+pub fn encode_append_string(data: &[u8], out: &mut String) {
+    // Safety: the encoder only appends chars of the alphabet, they are ASCII
+    encode(data, unsafe { out.as_mut_vec() });
+}
+
+pub fn decode_to_vec(data: &str) -> Result<Vec<u8>, DecodeError> {
+    let mut out = Vec::new();
+    decode(data.as_bytes(), &mut out)?;
+    Ok(out)
+}
+
+/// Encodes with padding at compile time:
+///
+/// ```
+/// use simd_base64::base64::encode_const;
+///
+/// const HELLO: [u8; 20] = encode_const(b"Hello, world!");
+/// assert_eq!(&HELLO, b"SGVsbG8sIHdvcmxkIQ==");
+/// ```
+///
+/// `OUT` has to be the padded length, otherwise the build fails:
+///
+/// ```compile_fail
+/// use simd_base64::base64::encode_const;
+///
+/// let hello: [u8; 18] = encode_const(b"Hello, world!");
+/// ```
+pub const fn encode_const<const IN: usize, const OUT: usize>(data: &[u8; IN]) -> [u8; OUT] {
+    const { assert!(OUT == IN.div_ceil(3) * 4, "OUT has to be the padded encoded length of IN") };
+
+    let mut out = [b'='; OUT];
+
+    let mut i = 0;
+    while i < IN {
+        let len = if IN - i < 3 { IN - i } else { 3 };
+
+        let mut bytes: u32 = 0;
+        let mut j = 0;
+        while j < len {
+            bytes |= (data[i + j] as u32) << (16 - j * 8);
+            j += 1;
+        }
+
+        // padding stays for the missing chars
+        let chars = encoded_len(len);
+        let o = i / 3 * 4;
+        let mut j = 0;
+        while j < chars {
+            out[o + j] = sextet_to_ascii((bytes >> (18 - j * 6)) as u8 & 0b111111, Alphabet::Standard);
+            j += 1;
+        }
+
+        i += len;
+    }
+
+    out
+}
+
+/// Decodes at compile time, invalid input fails the build:
+///
+/// ```compile_fail
+/// use simd_base64::base64::decode_const;
+///
+/// const HELLO: [u8; 13] = decode_const(b"SGVsbG8sIHdvcmxkIQ$=");
+/// ```
+///
+/// `OUT` has to be the decoded length. It depends on the padding in `data`,
+/// so outside of const context a wrong `OUT` panics at runtime.
+pub const fn decode_const<const IN: usize, const OUT: usize>(data: &[u8; IN]) -> [u8; OUT] {
+    // Same as common::remove_trailing_eq()
+    let len = match data.as_slice() {
+        [.., b'=', b'='] => IN - 2,
+        [.., b'='] => IN - 1,
+        _ => IN,
+    };
+    assert!(OUT == decoded_len(len), "OUT has to be the decoded length of IN");
+
+    let mut out = [0u8; OUT];
+
+    let mut i = 0;
+    while i < len {
+        let chunk_len = if len - i < 4 { len - i } else { 4 };
+
+        let mut bytes: u32 = 0;
+        let mut j = 0;
+        while j < chunk_len {
+            let Some(sextet) = ascii_to_sextet(data[i + j], Alphabet::Standard) else {
+                panic!("input of decode_const is not base64");
+            };
+            bytes <<= 6;
+            bytes |= sextet as u32;
+            j += 1;
+        }
+
+        // see decode()
+        bytes <<= (4 - chunk_len) * 6 + 8;
+
+        let decoded = bytes.to_be_bytes();
+        let o = i / 4 * 3;
+        let mut j = 0;
+        while j < decoded_len(chunk_len) {
+            out[o + j] = decoded[j];
+            j += 1;
+        }
+
+        i += chunk_len;
+    }
+
+    out
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -123,6 +265,31 @@ mod tests {
         assert_eq!(result, hello);
     }
 
+    #[test]
+    fn test_string() {
+        let mut encoded = encode_to_string(b"Hello, ");
+        encode_append_string(b"world!", &mut encoded);
+        assert_eq!(encoded, "SGVsbG8sIA==d29ybGQh");
+        assert!(encode_to_string(&(0..=255).collect::<Vec<u8>>()).is_ascii());
+
+        assert_eq!(decode_to_vec("SGVsbG8sIA==").unwrap(), b"Hello, ");
+        assert_eq!(decode_to_vec("SGV$"), Err(DecodeError::InvalidByte { offset: 3, byte: b'$' }));
+    }
+
+    #[test]
+    fn test_const() {
+        const ENCODED: [u8; 20] = encode_const(b"Hello, world!");
+        const DECODED: [u8; 13] = decode_const(&ENCODED);
+        assert_eq!(&ENCODED, b"SGVsbG8sIHdvcmxkIQ==");
+        assert_eq!(&DECODED, b"Hello, world!");
+
+        assert_eq!(encode_const::<0, 0>(b""), *b"");
+        assert_eq!(encode_const::<1, 4>(b"1"), *b"MQ==");
+        assert_eq!(encode_const::<2, 4>(b"12"), *b"MTI=");
+        assert_eq!(decode_const::<4, 1>(b"MQ=="), *b"1");
+        assert_eq!(decode_const::<3, 2>(b"MTI"), *b"12");
+    }
+
     #[test]
     fn test_encode_decode() {
         let message = b"0123456789";
@@ -0,0 +1,180 @@
+//! HTTP Basic authentication (RFC 7617): `Authorization: Basic <base64(user:password)>`.
+
+use std::fmt;
+
+use crate::base64_simd;
+use crate::DecodeError;
+
+// Longer credentials are rejected before decoding
+pub const MAX_ENCODED_LEN: usize = 1024;
+pub const MAX_DECODED_LEN: usize = MAX_ENCODED_LEN / 4 * 3;
+
+const LANES: usize = 16;
+
+const SCHEME: &str = "Basic";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Error {
+    TooLong,
+    InvalidScheme,
+    InvalidBase64(DecodeError),
+    MissingColon,
+    InvalidUtf8,
+    // RFC 7617: control characters are not allowed in user-id and password
+    ControlCharacter,
+    // A user-id can't contain ':', the first one separates the password
+    ColonInUser,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::TooLong => write!(f, "credentials are longer than {MAX_ENCODED_LEN} bytes"),
+            Error::InvalidScheme => write!(f, "not a Basic authorization"),
+            Error::InvalidBase64(err) => write!(f, "{err}"),
+            Error::MissingColon => write!(f, "no ':' between user-id and password"),
+            Error::InvalidUtf8 => write!(f, "credentials are not UTF-8"),
+            Error::ControlCharacter => write!(f, "control character in credentials"),
+            Error::ColonInUser => write!(f, "user-id contains ':'"),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::InvalidBase64(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+// Decoded credentials are kept inline, so parsing doesn't allocate
+#[derive(Clone)]
+pub struct Credentials {
+    buffer: [u8; MAX_DECODED_LEN],
+    len: usize,
+    colon: usize,
+}
+
+impl Credentials {
+    pub fn user(&self) -> &str {
+        // Safety: validated as UTF-8 by parse(), split on an ASCII char
+        unsafe { std::str::from_utf8_unchecked(&self.buffer[..self.colon]) }
+    }
+
+    pub fn password(&self) -> &str {
+        // Safety: see user()
+        unsafe { std::str::from_utf8_unchecked(&self.buffer[self.colon + 1..self.len]) }
+    }
+}
+
+impl fmt::Debug for Credentials {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Credentials")
+            .field("user", &self.user())
+            .field("password", &"***")
+            .finish()
+    }
+}
+
+/// Parses the value of an `Authorization` header, the scheme is case-insensitive.
+pub fn parse(header: &str) -> Result<Credentials, Error> {
+    let header = header.trim_matches([' ', '\t']);
+
+    let scheme = header.get(..SCHEME.len()).ok_or(Error::InvalidScheme)?;
+    let rest = &header[SCHEME.len()..];
+    if !scheme.eq_ignore_ascii_case(SCHEME) || !rest.starts_with(' ') {
+        return Err(Error::InvalidScheme);
+    }
+
+    let encoded = rest.trim_start_matches(' ');
+    if encoded.len() > MAX_ENCODED_LEN {
+        return Err(Error::TooLong);
+    }
+
+    let mut buffer = [0; MAX_DECODED_LEN];
+    let len = base64_simd::decode_slice::<LANES>(encoded.as_bytes(), &mut buffer)
+        .map_err(Error::InvalidBase64)?;
+    let decoded = &buffer[..len];
+
+    let text = std::str::from_utf8(decoded).map_err(|_| Error::InvalidUtf8)?;
+    if text.chars().any(char::is_control) {
+        return Err(Error::ControlCharacter);
+    }
+    let colon = text.find(':').ok_or(Error::MissingColon)?;
+
+    Ok(Credentials { buffer, len, colon })
+}
+
+/// Writes `Basic <base64(user:password)>`, the value of an `Authorization` header.
+pub fn encode(user: &str, password: &str, out: &mut String) -> Result<(), Error> {
+    if user.contains(':') {
+        return Err(Error::ColonInUser);
+    }
+    if user.chars().chain(password.chars()).any(char::is_control) {
+        return Err(Error::ControlCharacter);
+    }
+
+    let credentials = [user.as_bytes(), b":", password.as_bytes()].concat();
+
+    out.reserve(SCHEME.len() + 1 + base64_simd::padded_encoded_len(credentials.len()));
+
+    out.push_str(SCHEME);
+    out.push(' ');
+    base64_simd::encode_append_string::<LANES>(&credentials, out);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        // RFC 7617, section 2
+        let credentials = parse("Basic QWxhZGRpbjpvcGVuIHNlc2FtZQ==").unwrap();
+        assert_eq!(credentials.user(), "Aladdin");
+        assert_eq!(credentials.password(), "open sesame");
+
+        // RFC 7617, section 2.1
+        let credentials = parse("  bAsIc   dGVzdDoxMjPCow==").unwrap();
+        assert_eq!(credentials.user(), "test");
+        assert_eq!(credentials.password(), "123£");
+
+        // only the first ':' is a separator
+        let credentials = parse("Basic dXNlcjpwYXNzOndvcmQ=").unwrap();
+        assert_eq!(credentials.user(), "user");
+        assert_eq!(credentials.password(), "pass:word");
+    }
+
+    #[test]
+    fn test_encode() {
+        let mut header = String::new();
+        encode("Aladdin", "open sesame", &mut header).unwrap();
+        assert_eq!(header, "Basic QWxhZGRpbjpvcGVuIHNlc2FtZQ==");
+
+        assert_eq!(encode("a:b", "c", &mut header), Err(Error::ColonInUser));
+        assert_eq!(encode("a", "b\nc", &mut header), Err(Error::ControlCharacter));
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(parse("Bearer QWxhZGRpbjpvcGVuIHNlc2FtZQ==").unwrap_err(), Error::InvalidScheme);
+        assert_eq!(parse("BasicQWxhZGRpbjpvcGVuIHNlc2FtZQ==").unwrap_err(), Error::InvalidScheme);
+        assert_eq!(parse("Basic QWxhZGRpbg==").unwrap_err(), Error::MissingColon);
+        assert_eq!(parse("Basic /w==").unwrap_err(), Error::InvalidUtf8);
+        assert_eq!(parse("Basic YQpiOmM=").unwrap_err(), Error::ControlCharacter);
+        assert_eq!(
+            parse("Basic QWxh$GRpbjpvcGVuIHNlc2FtZQ==").unwrap_err(),
+            Error::InvalidBase64(DecodeError::InvalidByte { offset: 4, byte: b'$' })
+        );
+
+        let long = format!("Basic {}", "QUFB".repeat(MAX_ENCODED_LEN / 4 + 1));
+        assert_eq!(parse(&long).unwrap_err(), Error::TooLong);
+
+        let longest = format!("Basic {}", "QTpB".repeat(MAX_ENCODED_LEN / 4));
+        assert_eq!(parse(&longest).unwrap().user(), "A");
+    }
+}
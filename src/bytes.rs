@@ -0,0 +1,202 @@
+//! Encoding and decoding with `bytes` buffers, e.g. `BytesMut` of network code.
+//!
+//! Vectors are loaded straight from the input and stored straight into
+//! `chunk_mut()` of the output, only vectors which cross a chunk of either
+//! and the tail go through a copy.
+
+use std::simd::Simd;
+
+use ::bytes::{Buf, BufMut};
+
+use crate::base64_simd::{self, decoded_len, encoded_len};
+use crate::common;
+use crate::{Config, DecodeError};
+
+const LANES: usize = 32;
+
+pub fn encode_to_buf(data: &[u8], out: &mut impl BufMut) {
+    encode_to_buf_with(data, out, Config::STANDARD)
+}
+
+/// Panics if `out` has no room for the whole output.
+pub fn encode_to_buf_with(data: &[u8], out: &mut impl BufMut, config: Config) {
+    let final_size = match config.padding {
+        true => base64_simd::padded_encoded_len(data.len()),
+        false => encoded_len(data.len()),
+    };
+    assert!(out.remaining_mut() >= final_size, "no room for {final_size} bytes in the buffer");
+
+    let chunk_size = LANES - LANES / 4;
+    let mut input = data;
+
+    // main loop, encode_hot() uses only the first `chunk_size` bytes of a vector,
+    // so full vectors are loaded straight from the input
+    while input.len() >= LANES {
+        let asciis = base64_simd::encode_hot(Simd::from_slice(input), config.alphabet);
+        put_vector(out, asciis, LANES);
+        input = &input[chunk_size..];
+    }
+
+    // remainder, shorter than a vector, so it goes through a copy
+    for chunk in input.chunks(chunk_size) {
+        let mut bytes = [0u8; LANES];
+        bytes[..chunk.len()].copy_from_slice(chunk);
+
+        let asciis = base64_simd::encode_hot(Simd::from(bytes), config.alphabet);
+        put_vector(out, asciis, encoded_len(chunk.len()));
+    }
+
+    // padding
+    if config.padding {
+        match data.len() % 3 {
+            1 => out.put_slice(b"=="),
+            2 => out.put_slice(b"="),
+            _ => {},
+        }
+    }
+}
+
+pub fn decode_to_buf(data: impl Buf, out: &mut impl BufMut) -> Result<(), DecodeError> {
+    decode_to_buf_with(data, out, Config::STANDARD)
+}
+
+/// Decodes all of `data`, which may be non-contiguous, e.g. `Chain`.
+///
+/// On error the output has the bytes decoded before the invalid vector,
+/// the offset is from the start of `data`.
+/// Panics if `out` has no room for the whole output.
+pub fn decode_to_buf_with(mut data: impl Buf, out: &mut impl BufMut, config: Config) -> Result<(), DecodeError> {
+    // up to 2 chars may be padding, it is known only at the end
+    let padding = if config.padding { 2 } else { 0 };
+    let min_size = decoded_len(data.remaining().saturating_sub(padding));
+    assert!(out.remaining_mut() >= min_size, "no room for {min_size} bytes in the buffer");
+
+    let mut offset = 0;
+
+    // main loop, the last vector is left for the tail because it can end with padding
+    while data.remaining() > LANES {
+        // straight from the chunk if the vector is within it, otherwise it is gathered
+        let ascii = if data.chunk().len() >= LANES {
+            let ascii = Simd::from_slice(data.chunk());
+            data.advance(LANES);
+            ascii
+        } else {
+            let mut ascii = [0u8; LANES];
+            data.copy_to_slice(&mut ascii);
+            Simd::from(ascii)
+        };
+
+        let (sextets, ok) = base64_simd::decode_hot(ascii, config.alphabet);
+        if !ok {
+            return Err(common::find_invalid(ascii.as_array(), config.alphabet, false).add_offset(offset));
+        }
+        put_vector(out, sextets, decoded_len(LANES));
+        offset += LANES;
+    }
+
+    // tail
+    let mut tail = [0u8; LANES];
+    let len = data.remaining();
+    data.copy_to_slice(&mut tail[..len]);
+
+    let rest = if config.padding { common::remove_trailing_eq(&tail[..len]) } else { &tail[..len] };
+    if !rest.is_empty() {
+        let mut ascii = [b'A'; LANES];
+        ascii[..rest.len()].copy_from_slice(rest);
+
+        let (sextets, ok) = base64_simd::decode_hot(Simd::from(ascii), config.alphabet);
+        if !ok {
+            return Err(common::find_invalid(rest, config.alphabet, false).add_offset(offset));
+        }
+        put_vector(out, sextets, decoded_len(rest.len()));
+    }
+
+    Ok(())
+}
+
+// Stores the whole vector when there is room for it in the current chunk,
+// but commits only `len` bytes of it
+fn put_vector(out: &mut impl BufMut, vector: Simd<u8, LANES>, len: usize) {
+    let chunk = out.chunk_mut();
+    if chunk.len() >= LANES {
+        // Safety: the chunk has room for the whole vector, `len` bytes of it are initialized
+        unsafe {
+            chunk.as_mut_ptr().cast::<Simd<u8, LANES>>().write_unaligned(vector);
+            out.advance_mut(len);
+        }
+    } else {
+        out.put_slice(&vector.as_array()[..len]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ::bytes::{Bytes, BytesMut};
+
+    use super::*;
+    use crate::common::test_data;
+
+    #[test]
+    fn test_encode_decode() {
+        for len in [0, 1, 2, 3, 23, 24, 25, 47, 48, 49, 100, 1000] {
+            let data = test_data(len);
+            for config in [Config::STANDARD, Config::URL_SAFE_NO_PAD] {
+                let mut expected = Vec::new();
+                base64_simd::encode_with::<16>(&data, &mut expected, config);
+
+                let mut encoded = BytesMut::new();
+                encode_to_buf_with(&data, &mut encoded, config);
+                assert_eq!(encoded, expected);
+
+                let mut decoded = BytesMut::with_capacity(len);
+                decode_to_buf_with(encoded.freeze(), &mut decoded, config).unwrap();
+                assert_eq!(decoded, data);
+            }
+        }
+    }
+
+    #[test]
+    fn test_chained_input() {
+        let data = test_data(200);
+        let mut encoded = Vec::new();
+        base64_simd::encode::<16>(&data, &mut encoded);
+        let encoded = Bytes::from(encoded);
+
+        // vectors split between the buffers, padding included
+        for split in 0..=encoded.len() {
+            let chained = encoded.slice(..split).chain(encoded.slice(split..));
+            let mut decoded = Vec::new();
+            decode_to_buf(chained, &mut decoded).unwrap();
+            assert_eq!(decoded, data);
+        }
+
+        let mut invalid = encoded.to_vec();
+        invalid[100] = b'$';
+        let invalid = Bytes::from(invalid);
+        let chained = invalid.slice(..90).chain(invalid.slice(90..));
+        assert_eq!(
+            decode_to_buf(chained, &mut Vec::new()),
+            Err(DecodeError::InvalidByte { offset: 100, byte: b'$' })
+        );
+        assert_eq!(
+            decode_to_buf(&b"SGVsbG8$"[..], &mut Vec::new()),
+            Err(DecodeError::InvalidByte { offset: 7, byte: b'$' })
+        );
+    }
+
+    // A slice has no headroom after the output, so nothing is written past it
+    #[test]
+    fn test_exact_output() {
+        let data = test_data(100);
+
+        let mut encoded = [0u8; base64_simd::padded_encoded_len(100)];
+        encode_to_buf(&data, &mut &mut encoded[..]);
+
+        let mut decoded = [0u8; 101];
+        let mut out = &mut decoded[..100];
+        decode_to_buf(&encoded[..], &mut out).unwrap();
+        assert!(out.is_empty());
+        assert_eq!(decoded[..100], data);
+        assert_eq!(decoded[100], 0);
+    }
+}
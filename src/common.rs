@@ -1,5 +1,7 @@
 use std::{fmt, simd::{LaneCount, Simd, SimdElement, SupportedLaneCount}};
 
+use crate::{Alphabet, DecodeError};
+
 
 
 pub const SEXTET_UPPERS_OFFSET: u8 = 0;
@@ -31,6 +33,51 @@ pub fn pad_with_trailing_eq(data_len: usize, out: &mut Vec<u8>) {
     }
 }
 
+// Finds the first byte which is not from the base64 alphabet.
+// Simd validation only tells that there is such a byte somewhere,
+// so on the error path the input is scanned once more to report it.
+#[cold]
+pub fn find_invalid(data: &[u8], alphabet: Alphabet, ignore_whitespace: bool) -> DecodeError {
+    let offset = data
+        .iter()
+        .position(|&b| !alphabet.contains(b) && !(ignore_whitespace && b.is_ascii_whitespace()))
+        .expect("simd validation failed on valid base64");
+
+    DecodeError::InvalidByte { offset, byte: data[offset] }
+}
+
+// Input of the tests, the byte pattern doesn't repeat within a vector or every 256 bytes
+#[cfg(test)]
+pub fn test_data(len: usize) -> Vec<u8> {
+    (0..len).map(|i| (i * 37 + i / 256) as u8).collect()
+}
+
+// Inputs of the tests of the kernels, pseudo-random bytes in every lane
+#[cfg(test)]
+pub fn test_vectors<const N: usize>() -> impl Iterator<Item = [u8; N]> {
+    (0..1000u32).map(|n| std::array::from_fn(|i| (n.wrapping_mul(2654435761) >> (i % 24)) as u8 ^ (i as u8).wrapping_mul(37)))
+}
+
+// Encodes and decodes after a prefix, the output has to be the same as of the scalar codec
+#[cfg(test)]
+pub fn check_round_trip(
+    data: &[u8],
+    config: crate::Config,
+    encode: impl Fn(&[u8], &mut Vec<u8>, crate::Config),
+    decode: impl Fn(&[u8], &mut Vec<u8>, crate::Config) -> Result<(), DecodeError>,
+) {
+    let mut expected = Vec::new();
+    crate::base64::encode_with(data, &mut expected, config);
+
+    let mut encoded = b"prefix".to_vec();
+    encode(data, &mut encoded, config);
+    assert_eq!(encoded[6..], expected, "len={} {config:?}", data.len());
+
+    let mut decoded = b"prefix".to_vec();
+    decode(&encoded[6..], &mut decoded, config).unwrap();
+    assert_eq!(decoded[6..], *data, "len={} {config:?}", data.len());
+}
+
 // Functions for debug
 // Return string representation of bits (bytes ordered in big endian)
 #[allow(unused)]
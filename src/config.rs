@@ -0,0 +1,92 @@
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Alphabet {
+    // RFC 4648, section 4: `+` and `/`
+    Standard,
+    // RFC 4648, section 5: `-` and `_`
+    UrlSafe,
+}
+
+const STANDARD_CHARS: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+const URL_SAFE_CHARS: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+const STANDARD_LO_LUT: [u8; 16] = lo_lut(STANDARD_CHARS);
+const URL_SAFE_LO_LUT: [u8; 16] = lo_lut(URL_SAFE_CHARS);
+
+const STANDARD_DECODE_LUT: [u8; 128] = decode_lut(STANDARD_CHARS);
+const URL_SAFE_DECODE_LUT: [u8; 128] = decode_lut(URL_SAFE_CHARS);
+
+// Bit for every high nibble of a char, bit 7 is for non-printable chars.
+// A char is valid if the bit of its high nibble is unset in the entry of
+// its low nibble in the table from lo_lut().
+pub(crate) const HI_LUT: [u8; 16] = [
+    0x80, 0x80, 0x01, 0x02, 0x04, 0x08, 0x10, 0x20,
+    0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80, 0x80,
+];
+
+// For every low nibble sets bits of high nibbles which make an invalid char
+const fn lo_lut(chars: &[u8; 64]) -> [u8; 16] {
+    let mut lut = [0x80 | 0x3f; 16];
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        lut[(c & 0x0f) as usize] &= !HI_LUT[(c >> 4) as usize];
+        i += 1;
+    }
+    lut
+}
+
+// Sextet of every ASCII char, 0x80 for the chars which are not in the alphabet
+const fn decode_lut(chars: &[u8; 64]) -> [u8; 128] {
+    let mut lut = [0x80; 128];
+    let mut i = 0;
+    while i < chars.len() {
+        lut[chars[i] as usize] = i as u8;
+        i += 1;
+    }
+    lut
+}
+
+impl Alphabet {
+    pub const fn chars(self) -> &'static [u8; 64] {
+        match self {
+            Alphabet::Standard => STANDARD_CHARS,
+            Alphabet::UrlSafe => URL_SAFE_CHARS,
+        }
+    }
+
+    pub fn contains(self, byte: u8) -> bool {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' => true,
+            byte => byte == self.chars()[62] || byte == self.chars()[63],
+        }
+    }
+
+    pub(crate) const fn lo_lut(self) -> [u8; 16] {
+        match self {
+            Alphabet::Standard => STANDARD_LO_LUT,
+            Alphabet::UrlSafe => URL_SAFE_LO_LUT,
+        }
+    }
+
+    #[allow(unused)]
+    pub(crate) const fn decode_lut(self) -> &'static [u8; 128] {
+        match self {
+            Alphabet::Standard => &STANDARD_DECODE_LUT,
+            Alphabet::UrlSafe => &URL_SAFE_DECODE_LUT,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Config {
+    pub alphabet: Alphabet,
+    // Whether '=' is written by encoders and accepted at the end by decoders
+    pub padding: bool,
+}
+
+impl Config {
+    pub const STANDARD: Config = Config { alphabet: Alphabet::Standard, padding: true };
+    pub const STANDARD_NO_PAD: Config = Config { alphabet: Alphabet::Standard, padding: false };
+    pub const URL_SAFE: Config = Config { alphabet: Alphabet::UrlSafe, padding: true };
+    pub const URL_SAFE_NO_PAD: Config = Config { alphabet: Alphabet::UrlSafe, padding: false };
+}
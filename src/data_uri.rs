@@ -0,0 +1,220 @@
+//! `data:` URIs (RFC 2397): `data:[<mediatype>][;base64],<data>`.
+
+use std::fmt;
+
+use crate::base64_simd;
+use crate::DecodeError;
+
+const LANES: usize = 32;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DataUri {
+    // Lowercase `type/subtype`
+    pub media_type: String,
+    // Parameters with percent-decoded values, e.g. `charset`
+    pub params: Vec<(String, String)>,
+    pub base64: bool,
+    pub data: Vec<u8>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    NotDataUri,
+    MissingComma,
+    InvalidMediaType,
+    // Offset is from the start of the URI
+    InvalidPercentEncoding { offset: usize },
+    // Offset is from the start of the data part, after percent-decoding
+    InvalidBase64(DecodeError),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::NotDataUri => write!(f, "not a data: URI"),
+            Error::MissingComma => write!(f, "no ',' before the data"),
+            Error::InvalidMediaType => write!(f, "invalid media type"),
+            Error::InvalidPercentEncoding { offset } => write!(f, "invalid percent-encoding at offset {offset}"),
+            Error::InvalidBase64(err) => write!(f, "{err}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::InvalidBase64(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+const SCHEME: &str = "data:";
+const BASE64_SUFFIX: &str = ";base64,";
+
+/// Parses a data URI, the data is percent-decoded and/or base64-decoded.
+pub fn parse(uri: &str) -> Result<DataUri, Error> {
+    let scheme = uri.get(..SCHEME.len()).ok_or(Error::NotDataUri)?;
+    if !scheme.eq_ignore_ascii_case(SCHEME) {
+        return Err(Error::NotDataUri);
+    }
+
+    let header_start = SCHEME.len();
+    let comma = uri[header_start..].find(',').ok_or(Error::MissingComma)? + header_start;
+    let header = &uri[header_start..comma];
+    let data_start = comma + 1;
+
+    let mut params = Vec::new();
+    let mut base64 = false;
+    let mut param_start = header_start;
+    let mut media_type = None;
+    for param in header.split(';') {
+        let offset = param_start;
+        param_start += param.len() + 1;
+
+        if media_type.is_none() {
+            media_type = Some(param.trim());
+        } else if param_start > comma && param.trim().eq_ignore_ascii_case("base64") {
+            // `;base64` is always the last one
+            base64 = true;
+        } else {
+            let (name, value) = param.split_once('=').ok_or(Error::InvalidMediaType)?;
+            let mut decoded = Vec::new();
+            percent_decode(value.as_bytes(), offset + name.len() + 1, &mut decoded)?;
+            let value = String::from_utf8(decoded).map_err(|_| Error::InvalidMediaType)?;
+            params.push((name.trim().to_ascii_lowercase(), value));
+        }
+    }
+    let media_type = media_type.unwrap_or("");
+
+    // RFC 2397: defaults to text/plain;charset=US-ASCII
+    let media_type = if media_type.is_empty() {
+        if !params.iter().any(|(name, _)| name == "charset") {
+            params.insert(0, ("charset".to_owned(), "US-ASCII".to_owned()));
+        }
+        "text/plain".to_owned()
+    } else {
+        match media_type.split_once('/') {
+            Some((t, s)) if !t.is_empty() && !s.is_empty() => media_type.to_ascii_lowercase(),
+            _ => return Err(Error::InvalidMediaType),
+        }
+    };
+
+    let body = &uri.as_bytes()[data_start..];
+    let mut data = Vec::new();
+    if base64 {
+        // Percent-encoded base64 is rare, so decode the body in place when possible
+        let result = if body.contains(&b'%') {
+            let mut unescaped = Vec::with_capacity(body.len());
+            percent_decode(body, data_start, &mut unescaped)?;
+            base64_simd::decode_ignore_whitespace::<LANES>(&unescaped, &mut data)
+        } else {
+            base64_simd::decode_ignore_whitespace::<LANES>(body, &mut data)
+        };
+        result.map_err(Error::InvalidBase64)?;
+    } else {
+        percent_decode(body, data_start, &mut data)?;
+    }
+
+    Ok(DataUri {
+        media_type,
+        params,
+        base64,
+        data,
+    })
+}
+
+/// Writes `data:<media_type>;base64,<data>`, the output is reserved only once.
+pub fn build(media_type: &str, data: &[u8], out: &mut String) {
+    let encoded_len = base64_simd::padded_encoded_len(data.len());
+    let total_len = SCHEME.len() + media_type.len() + BASE64_SUFFIX.len() + encoded_len;
+
+    out.reserve_exact(total_len);
+
+    out.push_str(SCHEME);
+    out.push_str(media_type);
+    out.push_str(BASE64_SUFFIX);
+    base64_simd::encode_append_string::<LANES>(data, out);
+}
+
+fn hex_digit(byte: u8) -> Option<u8> {
+    (byte as char).to_digit(16).map(|d| d as u8)
+}
+
+// `offset` is the position of the input in the URI for errors
+fn percent_decode(input: &[u8], offset: usize, out: &mut Vec<u8>) -> Result<(), Error> {
+    out.reserve(input.len());
+
+    let mut i = 0;
+    while i < input.len() {
+        let byte = match input[i] {
+            b'%' => {
+                let hi = input.get(i + 1).copied().and_then(hex_digit);
+                let lo = input.get(i + 2).copied().and_then(hex_digit);
+                let (Some(hi), Some(lo)) = (hi, lo) else {
+                    return Err(Error::InvalidPercentEncoding { offset: offset + i });
+                };
+                i += 2;
+                hi << 4 | lo
+            },
+            byte => byte,
+        };
+        out.push(byte);
+        i += 1;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::test_data;
+
+    #[test]
+    fn test_parse() {
+        let uri = parse("data:,A%20brief%20note").unwrap();
+        assert_eq!(uri.media_type, "text/plain");
+        assert_eq!(uri.params, [("charset".to_owned(), "US-ASCII".to_owned())]);
+        assert!(!uri.base64);
+        assert_eq!(uri.data, b"A brief note");
+
+        let uri = parse("data:Image/GIF;name=a%20b;base64,R0lGODdhMAAwAPAAAAAAAP///ywAAAAAMAAw").unwrap();
+        assert_eq!(uri.media_type, "image/gif");
+        assert_eq!(uri.params, [("name".to_owned(), "a b".to_owned())]);
+        assert!(uri.base64);
+        assert!(uri.data.starts_with(b"GIF87a0\x000\x00"));
+
+        let uri = parse("DATA:;charset=utf-8;base64,SGVsbG8s%20IHdvcmxkIQ%3D%3D").unwrap();
+        assert_eq!(uri.media_type, "text/plain");
+        assert_eq!(uri.params, [("charset".to_owned(), "utf-8".to_owned())]);
+        assert_eq!(uri.data, b"Hello, world!");
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(parse("http://example.com"), Err(Error::NotDataUri));
+        assert_eq!(parse("data:text/plain"), Err(Error::MissingComma));
+        assert_eq!(parse("data:text;base64,AAAA"), Err(Error::InvalidMediaType));
+        assert_eq!(parse("data:,abc%2"), Err(Error::InvalidPercentEncoding { offset: 9 }));
+        assert_eq!(
+            parse("data:;base64,SGV$"),
+            Err(Error::InvalidBase64(DecodeError::InvalidByte { offset: 3, byte: b'$' }))
+        );
+    }
+
+    #[test]
+    fn test_build() {
+        for len in 0..100 {
+            let data = test_data(len);
+
+            let mut uri = String::new();
+            build("image/png", &data, &mut uri);
+            assert_eq!(uri.len(), uri.capacity());
+
+            let parsed = parse(&uri).unwrap();
+            assert_eq!(parsed.media_type, "image/png");
+            assert_eq!(parsed.data, data);
+        }
+    }
+}
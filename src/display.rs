@@ -0,0 +1,98 @@
+//! Base64 for `{}` in `format!`, `write!` and log lines without an intermediate `Vec`.
+
+use std::fmt;
+use std::simd::Simd;
+
+use crate::base64_simd::{self, encoded_len};
+use crate::Config;
+
+const LANES: usize = 32;
+const CHUNK_SIZE: usize = LANES - LANES / 4;
+
+// Vectors encoded into the stack buffer between write_str() calls
+const BUFFER_VECTORS: usize = 8;
+
+#[derive(Debug, Clone, Copy)]
+pub struct Base64Display<'a> {
+    data: &'a [u8],
+    config: Config,
+}
+
+impl<'a> Base64Display<'a> {
+    pub fn new(data: &'a [u8]) -> Self {
+        Self::with_config(data, Config::STANDARD)
+    }
+
+    pub fn with_config(data: &'a [u8], config: Config) -> Self {
+        Base64Display { data, config }
+    }
+}
+
+/// Shorthand for [`Base64Display::new`]: `write!(f, "{}", b64(&bytes))`.
+pub fn b64(data: &[u8]) -> Base64Display<'_> {
+    Base64Display::new(data)
+}
+
+impl fmt::Display for Base64Display<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut buffer = [0u8; LANES * BUFFER_VECTORS];
+
+        for group in self.data.chunks(CHUNK_SIZE * BUFFER_VECTORS) {
+            let mut len = 0;
+            for chunk in group.chunks(CHUNK_SIZE) {
+                let mut bytes = [0u8; LANES];
+                bytes[..chunk.len()].copy_from_slice(chunk);
+
+                // Only the last chunk is partial, so the whole vector always fits
+                let asciis = base64_simd::encode_hot(Simd::from(bytes), self.config.alphabet);
+                buffer[len..len + LANES].copy_from_slice(asciis.as_array());
+                len += encoded_len(chunk.len());
+            }
+
+            // Safety: the encoder only produces ASCII
+            f.write_str(unsafe { std::str::from_utf8_unchecked(&buffer[..len]) })?;
+        }
+
+        if self.config.padding {
+            match self.data.len() % 3 {
+                1 => f.write_str("==")?,
+                2 => f.write_str("=")?,
+                _ => {},
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::test_data;
+
+    #[test]
+    fn test_display() {
+        assert_eq!(format!("<{}>", b64(b"Hello")), "<SGVsbG8=>");
+        assert_eq!(b64(b"").to_string(), "");
+
+        let display = Base64Display::with_config(b"\xfb\xff", Config::URL_SAFE_NO_PAD);
+        assert_eq!(display.to_string(), "-_8");
+    }
+
+    #[test]
+    fn test_encode_decode() {
+        let configs = [Config::STANDARD, Config::STANDARD_NO_PAD, Config::URL_SAFE, Config::URL_SAFE_NO_PAD];
+
+        // several write_str() calls for the longer ones
+        for len in [0, 1, 2, 3, 23, 24, 25, 191, 192, 193, 1000] {
+            let data = test_data(len);
+            for config in configs {
+                let mut expected = Vec::new();
+                base64_simd::encode_with::<16>(&data, &mut expected, config);
+
+                let display = Base64Display::with_config(&data, config);
+                assert_eq!(display.to_string().as_bytes(), expected);
+            }
+        }
+    }
+}
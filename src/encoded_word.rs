@@ -0,0 +1,331 @@
+//! RFC 2047 encoded-words for email headers: `=?charset?B?...?=` and `=?charset?Q?...?=`.
+
+use std::fmt;
+
+use crate::base64;
+use crate::base64_simd;
+
+// RFC 2047, section 2: an encoded-word may not be more than 75 characters long
+pub const MAX_WORD_LEN: usize = 75;
+
+// Encoded-words are separated by folding whitespace
+const WORD_SEPARATOR: &str = "\r\n ";
+
+const LANES: usize = 16;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    UnsupportedCharset(String),
+    InvalidBase64,
+    InvalidQuotedPrintable,
+    InvalidText,
+    MaxLineTooShort(usize),
+    CharsetTooLong(String),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::UnsupportedCharset(charset) => write!(f, "unsupported charset {charset:?}"),
+            Error::InvalidBase64 => write!(f, "invalid base64 in encoded-word"),
+            Error::InvalidQuotedPrintable => write!(f, "invalid Q-encoding in encoded-word"),
+            Error::InvalidText => write!(f, "encoded-word text does not match its charset"),
+            Error::MaxLineTooShort(min) => write!(f, "max_line is too short, at least {min} is needed"),
+            Error::CharsetTooLong(charset) => write!(f, "charset {charset:?} leaves no room for text in an encoded-word"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// B-encodes `text` into one or more encoded-words.
+///
+/// Words are split on char boundaries so that none is longer than
+/// `max_line` (capped at 75) and are separated by folding whitespace.
+/// Fails with [`Error::MaxLineTooShort`] if a word of `max_line` can't hold
+/// the longest encoding of a single char, or with [`Error::CharsetTooLong`]
+/// if not even a word of 75 can.
+pub fn encode(text: &str, charset: &str, max_line: usize) -> Result<String, Error> {
+    // 4 chars of base64 carry 3 bytes of text, a char of 4 bytes needs 8
+    let max_bytes = payload_len(charset, max_line, 8)? / 4 * 3;
+    Ok(encode_words(text, charset, 'B', max_bytes, |c| c.len_utf8(), |piece, out| {
+        base64::encode(piece.as_bytes(), out);
+    }))
+}
+
+/// Q-encodes `text` into one or more encoded-words, see [`encode`].
+pub fn encode_q(text: &str, charset: &str, max_line: usize) -> Result<String, Error> {
+    // every byte of a char of 4 bytes may be =XX
+    let max_bytes = payload_len(charset, max_line, 12)?;
+    Ok(encode_words(text, charset, 'Q', max_bytes, q_encoded_len, |piece, out| {
+        for &byte in piece.as_bytes() {
+            q_encode_byte(byte, out);
+        }
+    }))
+}
+
+/// Decodes every encoded-word found in a header value.
+///
+/// Text outside of encoded-words is kept as is, whitespace between two
+/// adjacent encoded-words is dropped. Strings that only look like
+/// encoded-words (e.g. unknown encoding) are kept as is too.
+pub fn decode_header(header: &str) -> Result<String, Error> {
+    let mut out = String::with_capacity(header.len());
+
+    // bytes of adjacent encoded-words with the same charset are joined
+    // before conversion, because a character might be split between them
+    let mut pending: Vec<u8> = Vec::new();
+    let mut pending_charset = "";
+    let mut after_word = false;
+
+    let mut rest = header;
+    while let Some(start) = rest.find("=?") {
+        let Some((word, len)) = parse_word(&rest[start..]) else {
+            flush(&mut pending, pending_charset, &mut out)?;
+            out.push_str(&rest[..start + 2]);
+            rest = &rest[start + 2..];
+            after_word = false;
+            continue;
+        };
+
+        let between = &rest[..start];
+        let adjacent = after_word && between.bytes().all(|b| b.is_ascii_whitespace());
+        if !adjacent || !word.charset.eq_ignore_ascii_case(pending_charset) {
+            flush(&mut pending, pending_charset, &mut out)?;
+        }
+        if !adjacent {
+            out.push_str(between);
+        }
+
+        match word.encoding {
+            b'B' | b'b' => {
+                base64_simd::decode::<LANES>(word.text.as_bytes(), &mut pending)
+                    .map_err(|_| Error::InvalidBase64)?;
+            },
+            _ /* Q */ => q_decode(word.text.as_bytes(), &mut pending)?,
+        }
+        pending_charset = word.charset;
+        after_word = true;
+
+        rest = &rest[start + len..];
+    }
+
+    flush(&mut pending, pending_charset, &mut out)?;
+    out.push_str(rest);
+
+    Ok(out)
+}
+
+struct Word<'a> {
+    charset: &'a str,
+    encoding: u8,
+    text: &'a str,
+}
+
+// Parses `=?charset?encoding?text?=` at the start of input,
+// returns the word and its length
+fn parse_word(input: &str) -> Option<(Word<'_>, usize)> {
+    let inner = input.strip_prefix("=?")?;
+
+    let (charset, inner) = inner.split_once('?')?;
+    let (encoding, inner) = inner.split_once('?')?;
+    let text_len = inner.find("?=")?;
+    let text = &inner[..text_len];
+    let len = input.len() - inner[text_len + 2..].len();
+
+    let encoding = match encoding.as_bytes() {
+        [e @ (b'B' | b'b' | b'Q' | b'q')] => *e,
+        _ => return None,
+    };
+    let valid_text = text.bytes().all(|b| b.is_ascii_graphic() && b != b'?');
+    if charset.is_empty() || !valid_text {
+        return None;
+    }
+
+    // RFC 2231 language suffix: charset*language
+    let charset = charset.split('*').next().unwrap_or(charset);
+
+    Some((Word { charset, encoding, text }, len))
+}
+
+fn flush(pending: &mut Vec<u8>, charset: &str, out: &mut String) -> Result<(), Error> {
+    if pending.is_empty() {
+        return Ok(());
+    }
+
+    match charset.to_ascii_lowercase().as_str() {
+        "utf-8" | "utf8" | "us-ascii" => {
+            let text = std::str::from_utf8(pending).map_err(|_| Error::InvalidText)?;
+            out.push_str(text);
+        },
+        "iso-8859-1" | "latin1" => {
+            out.extend(pending.iter().map(|&b| b as char));
+        },
+        _ => return Err(Error::UnsupportedCharset(charset.to_owned())),
+    }
+
+    pending.clear();
+    Ok(())
+}
+
+// Space left for the encoded text in a word,
+// at least `min_payload` so that any char fits into a single word
+fn payload_len(charset: &str, max_line: usize, min_payload: usize) -> Result<usize, Error> {
+    let overhead = "=??X??=".len() + charset.len();
+
+    let min_line = overhead + min_payload;
+    if min_line > MAX_WORD_LEN {
+        return Err(Error::CharsetTooLong(charset.to_owned()));
+    }
+    if max_line < min_line {
+        return Err(Error::MaxLineTooShort(min_line));
+    }
+
+    Ok(usize::min(max_line, MAX_WORD_LEN) - overhead)
+}
+
+fn encode_words(
+    text: &str,
+    charset: &str,
+    encoding: char,
+    max_len: usize,
+    char_len: impl Fn(char) -> usize,
+    encode_piece: impl Fn(&str, &mut Vec<u8>),
+) -> String {
+    let mut out = Vec::with_capacity(text.len() * 2);
+
+    let mut rest = text;
+    loop {
+        // the longest prefix which fits into a single word
+        let mut piece_len = 0;
+        let mut encoded_len = 0;
+        for c in rest.chars() {
+            encoded_len += char_len(c);
+            if encoded_len > max_len {
+                break;
+            }
+            piece_len += c.len_utf8();
+        }
+        let (piece, tail) = rest.split_at(piece_len);
+
+        out.extend_from_slice(b"=?");
+        out.extend_from_slice(charset.as_bytes());
+        out.push(b'?');
+        out.push(encoding as u8);
+        out.push(b'?');
+        encode_piece(piece, &mut out);
+        out.extend_from_slice(b"?=");
+
+        if tail.is_empty() {
+            break;
+        }
+        out.extend_from_slice(WORD_SEPARATOR.as_bytes());
+        rest = tail;
+    }
+
+    String::from_utf8(out).expect("encoded-words are ASCII")
+}
+
+// Chars allowed unencoded in a Q-encoded word, see RFC 2047, section 5 (3)
+fn q_is_plain(byte: u8) -> bool {
+    matches!(byte, b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'!' | b'*' | b'+' | b'-' | b'/')
+}
+
+fn q_encoded_len(c: char) -> usize {
+    let mut buf = [0; 4];
+    c.encode_utf8(&mut buf)
+        .bytes()
+        .map(|b| if q_is_plain(b) || b == b' ' { 1 } else { 3 })
+        .sum()
+}
+
+fn q_encode_byte(byte: u8, out: &mut Vec<u8>) {
+    const HEX: &[u8; 16] = b"0123456789ABCDEF";
+
+    match byte {
+        b' ' => out.push(b'_'),
+        b if q_is_plain(b) => out.push(b),
+        b => out.extend_from_slice(&[b'=', HEX[(b >> 4) as usize], HEX[(b & 0xf) as usize]]),
+    }
+}
+
+fn q_decode(text: &[u8], out: &mut Vec<u8>) -> Result<(), Error> {
+    let mut bytes = text.iter();
+    while let Some(&byte) = bytes.next() {
+        let decoded = match byte {
+            b'_' => b' ',
+            b'=' => {
+                let hi = bytes.next().and_then(|&b| (b as char).to_digit(16));
+                let lo = bytes.next().and_then(|&b| (b as char).to_digit(16));
+                match (hi, lo) {
+                    (Some(hi), Some(lo)) => (hi << 4 | lo) as u8,
+                    _ => return Err(Error::InvalidQuotedPrintable),
+                }
+            },
+            b => b,
+        };
+        out.push(decoded);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encode() {
+        assert_eq!(encode("Hello, world!", "UTF-8", 75).unwrap(), "=?UTF-8?B?SGVsbG8sIHdvcmxkIQ==?=");
+        assert_eq!(encode_q("Hello, world!", "UTF-8", 75).unwrap(), "=?UTF-8?Q?Hello=2C_world!?=");
+    }
+
+    #[test]
+    fn test_encode_max_line() {
+        // "=?UTF-8?B??=" and 8 chars of base64 or 12 of Q-encoding
+        assert_eq!(encode("😀😀", "UTF-8", 20), Ok("=?UTF-8?B?8J+YgA==?=\r\n =?UTF-8?B?8J+YgA==?=".to_owned()));
+        assert_eq!(encode("é", "UTF-8", 19), Err(Error::MaxLineTooShort(20)));
+        assert_eq!(encode_q("😀", "UTF-8", 24), Ok("=?UTF-8?Q?=F0=9F=98=80?=".to_owned()));
+        assert_eq!(encode_q("é", "UTF-8", 23), Err(Error::MaxLineTooShort(24)));
+
+        // the longest charsets which fit into a word of 75 are 60 and 56
+        let charset = "x".repeat(60);
+        assert_eq!(encode("a", &charset, 100).unwrap().len(), 7 + 60 + 4);
+        assert_eq!(encode_q("a", &charset, 100), Err(Error::CharsetTooLong(charset.clone())));
+        let charset = "x".repeat(61);
+        assert_eq!(encode("a", &charset, 100), Err(Error::CharsetTooLong(charset)));
+    }
+
+    #[test]
+    fn test_encode_split() {
+        let text = "Ünïcödé ".repeat(10);
+
+        for encoded in [encode(&text, "UTF-8", 75).unwrap(), encode_q(&text, "UTF-8", 40).unwrap()] {
+            let words: Vec<_> = encoded.split(WORD_SEPARATOR).collect();
+            assert!(words.len() > 1);
+            assert!(words.iter().all(|w| w.len() <= MAX_WORD_LEN));
+            assert_eq!(decode_header(&encoded).unwrap(), text);
+        }
+    }
+
+    #[test]
+    fn test_decode_header() {
+        let header = "Re: =?utf-8?B?SGVsbG8s?= =?UTF-8?Q?_w=C3=B6rld?= (=?iso-8859-1?q?caf=E9?=)";
+        assert_eq!(decode_header(header).unwrap(), "Re: Hello, wörld (café)");
+
+        // not encoded-words
+        let header = "a =? b =?utf-8?X?abc?= c";
+        assert_eq!(decode_header(header).unwrap(), header);
+    }
+
+    #[test]
+    fn test_decode_header_invalid() {
+        assert_eq!(decode_header("=?utf-8?B?SGV$bG8=?="), Err(Error::InvalidBase64));
+        assert_eq!(decode_header("=?utf-8?Q?caf=E?="), Err(Error::InvalidQuotedPrintable));
+        assert_eq!(decode_header("=?utf-8?Q?caf=E9?="), Err(Error::InvalidText));
+        assert_eq!(
+            decode_header("=?koi8-r?B?SGVsbG8=?="),
+            Err(Error::UnsupportedCharset("koi8-r".to_owned()))
+        );
+    }
+}
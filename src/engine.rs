@@ -0,0 +1,228 @@
+//! One interface over the scalar and SIMD codecs, so code can be generic over the backend.
+
+use std::simd::{LaneCount, SupportedLaneCount};
+
+use crate::{base64, base64_simd};
+use crate::{Config, DecodeError};
+
+pub trait Engine {
+    fn config(&self) -> Config;
+
+    /// Length of the output of [`Engine::encode_into`], with padding if it is enabled.
+    fn encoded_len(&self, decoded_len: usize) -> usize {
+        match self.config().padding {
+            true => base64_simd::padded_encoded_len(decoded_len),
+            false => base64_simd::encoded_len(decoded_len),
+        }
+    }
+
+    /// Upper bound of the output of [`Engine::decode_into`],
+    /// it is exact for input without padding.
+    fn decoded_len(&self, encoded_len: usize) -> usize {
+        base64_simd::decoded_len(encoded_len)
+    }
+
+    /// Appends base64 of `data` to `out`.
+    fn encode_into(&self, data: &[u8], out: &mut Vec<u8>);
+
+    /// Appends decoded `data` to `out`, on error `out` is left as it was.
+    fn decode_into(&self, data: &[u8], out: &mut Vec<u8>) -> Result<(), DecodeError>;
+
+    fn encode_to_string(&self, data: &[u8]) -> String {
+        let mut out = String::with_capacity(self.encoded_len(data.len()));
+        // Safety: engines only append chars of the alphabet, they are ASCII
+        self.encode_into(data, unsafe { out.as_mut_vec() });
+        out
+    }
+
+    fn decode_to_vec(&self, data: &[u8]) -> Result<Vec<u8>, DecodeError> {
+        let mut out = Vec::new();
+        self.decode_into(data, &mut out)?;
+        Ok(out)
+    }
+}
+
+// One byte at a time, see base64.rs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ScalarEngine {
+    config: Config,
+}
+
+impl ScalarEngine {
+    pub const fn new(config: Config) -> Self {
+        ScalarEngine { config }
+    }
+}
+
+impl Engine for ScalarEngine {
+    fn config(&self) -> Config {
+        self.config
+    }
+
+    fn encode_into(&self, data: &[u8], out: &mut Vec<u8>) {
+        base64::encode_with(data, out, self.config)
+    }
+
+    fn decode_into(&self, data: &[u8], out: &mut Vec<u8>) -> Result<(), DecodeError> {
+        base64::decode_with(data, out, self.config)
+    }
+}
+
+// Vectors of N bytes, see base64_simd.rs
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SimdEngine<const N: usize> {
+    config: Config,
+}
+
+impl<const N: usize> SimdEngine<N>
+where
+    LaneCount<N>: SupportedLaneCount
+{
+    pub const fn new(config: Config) -> Self {
+        SimdEngine { config }
+    }
+}
+
+impl<const N: usize> Engine for SimdEngine<N>
+where
+    LaneCount<N>: SupportedLaneCount
+{
+    fn config(&self) -> Config {
+        self.config
+    }
+
+    fn encode_into(&self, data: &[u8], out: &mut Vec<u8>) {
+        base64_simd::encode_with::<N>(data, out, self.config)
+    }
+
+    fn decode_into(&self, data: &[u8], out: &mut Vec<u8>) -> Result<(), DecodeError> {
+        base64_simd::decode_with::<N>(data, out, self.config)
+    }
+}
+
+// The widest vectors of the target, narrower ones only waste registers
+#[cfg(target_feature = "avx512bw")]
+const AUTO_LANES: usize = 64;
+#[cfg(all(target_feature = "avx2", not(target_feature = "avx512bw")))]
+const AUTO_LANES: usize = 32;
+#[cfg(not(any(target_feature = "avx2", target_feature = "avx512bw")))]
+const AUTO_LANES: usize = 16;
+
+/// Picks a backend for every call: the scalar one for input shorter than
+/// a vector, which would go through the remainder copy only, and SIMD
+/// with the widest vectors of the target otherwise.
+///
+/// With the `neon` feature on AArch64 the SIMD one is [`neon`](crate::neon).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AutoEngine {
+    scalar: ScalarEngine,
+    simd: SimdEngine<AUTO_LANES>,
+}
+
+impl AutoEngine {
+    pub const fn new(config: Config) -> Self {
+        AutoEngine {
+            scalar: ScalarEngine::new(config),
+            simd: SimdEngine::new(config),
+        }
+    }
+}
+
+impl Default for AutoEngine {
+    fn default() -> Self {
+        AutoEngine::new(Config::STANDARD)
+    }
+}
+
+impl Engine for AutoEngine {
+    fn config(&self) -> Config {
+        self.simd.config
+    }
+
+    fn encode_into(&self, data: &[u8], out: &mut Vec<u8>) {
+        match data.len() < AUTO_LANES {
+            true => self.scalar.encode_into(data, out),
+            #[cfg(all(target_arch = "aarch64", feature = "neon"))]
+            false => crate::neon::encode_with(data, out, self.config()),
+            #[cfg(not(all(target_arch = "aarch64", feature = "neon")))]
+            false => self.simd.encode_into(data, out),
+        }
+    }
+
+    fn decode_into(&self, data: &[u8], out: &mut Vec<u8>) -> Result<(), DecodeError> {
+        match data.len() < AUTO_LANES {
+            true => self.scalar.decode_into(data, out),
+            #[cfg(all(target_arch = "aarch64", feature = "neon"))]
+            false => crate::neon::decode_with(data, out, self.config()),
+            #[cfg(not(all(target_arch = "aarch64", feature = "neon")))]
+            false => self.simd.decode_into(data, out),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::test_data;
+
+    const CONFIGS: [Config; 4] = [Config::STANDARD, Config::STANDARD_NO_PAD, Config::URL_SAFE, Config::URL_SAFE_NO_PAD];
+
+    // Every engine has to produce the same as the scalar one, errors included
+    fn check_engine(engine: &impl Engine) {
+        let reference = ScalarEngine::new(engine.config());
+
+        for len in 0..200 {
+            let data = test_data(len);
+
+            let encoded = engine.encode_to_string(&data);
+            assert_eq!(encoded, reference.encode_to_string(&data));
+            assert_eq!(encoded.len(), engine.encoded_len(data.len()));
+            assert!(engine.decoded_len(encoded.len()) >= len);
+
+            let mut decoded = vec![1, 2, 3];
+            engine.decode_into(encoded.as_bytes(), &mut decoded).unwrap();
+            assert_eq!(decoded[..3], [1, 2, 3]);
+            assert_eq!(decoded[3..], data);
+
+            // an invalid byte in every position of the unpadded part
+            let unpadded = encoded.trim_end_matches('=');
+            for pos in (0..unpadded.len()).step_by(7) {
+                let mut invalid = encoded.clone().into_bytes();
+                invalid[pos] = b'*';
+                let mut out = vec![1];
+                assert_eq!(
+                    engine.decode_into(&invalid, &mut out),
+                    Err(DecodeError::InvalidByte { offset: pos, byte: b'*' })
+                );
+                assert_eq!(reference.decode_to_vec(&invalid), engine.decode_to_vec(&invalid));
+                assert_eq!(out, [1]);
+            }
+        }
+    }
+
+    #[test]
+    fn test_engines() {
+        for config in CONFIGS {
+            check_engine(&ScalarEngine::new(config));
+            check_engine(&SimdEngine::<8>::new(config));
+            check_engine(&SimdEngine::<16>::new(config));
+            check_engine(&SimdEngine::<32>::new(config));
+            check_engine(&SimdEngine::<64>::new(config));
+            check_engine(&AutoEngine::new(config));
+        }
+    }
+
+    #[test]
+    fn test_dyn_engine() {
+        let engines: [Box<dyn Engine>; 3] = [
+            Box::new(ScalarEngine::new(Config::URL_SAFE_NO_PAD)),
+            Box::new(SimdEngine::<16>::new(Config::URL_SAFE_NO_PAD)),
+            Box::new(AutoEngine::new(Config::URL_SAFE_NO_PAD)),
+        ];
+        for engine in &engines {
+            assert_eq!(engine.encode_to_string(&[0xfb, 0xff]), "-_8");
+            assert_eq!(engine.decode_to_vec(b"-_8").unwrap(), [0xfb, 0xff]);
+        }
+        assert_eq!(AutoEngine::default().encode_to_string(b"Hello"), "SGVsbG8=");
+    }
+}
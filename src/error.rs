@@ -0,0 +1,34 @@
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DecodeError {
+    // Byte which is not from the base64 alphabet, offset is from the start of the input
+    InvalidByte { offset: usize, byte: u8 },
+}
+
+impl DecodeError {
+    pub fn offset(&self) -> usize {
+        match *self {
+            DecodeError::InvalidByte { offset, .. } => offset,
+        }
+    }
+
+    // Makes the offset relative to an outer input, where the decoded part starts at `start`
+    pub(crate) fn add_offset(self, start: usize) -> Self {
+        match self {
+            DecodeError::InvalidByte { offset, byte } => DecodeError::InvalidByte { offset: start + offset, byte },
+        }
+    }
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            DecodeError::InvalidByte { offset, byte } => {
+                write!(f, "invalid base64 byte {:?} at offset {offset}", byte as char)
+            },
+        }
+    }
+}
+
+impl std::error::Error for DecodeError {}
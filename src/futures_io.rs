@@ -0,0 +1,162 @@
+//! Base64 encoding writer and decoding reader for `futures-io`, see tokio.rs.
+
+use std::io;
+use std::pin::Pin;
+use std::task::{ready, Context, Poll};
+
+use ::futures_io::{AsyncRead, AsyncWrite};
+
+use crate::stream::{Decoder, Encoder};
+use crate::Config;
+
+/// Encodes everything written to it into the inner writer.
+///
+/// Flushing keeps up to 2 bytes which don't make a whole group yet,
+/// they are written with padding on close.
+pub struct EncoderWriter<W> {
+    inner: W,
+    encoder: Encoder,
+}
+
+impl<W: AsyncWrite + Unpin> EncoderWriter<W> {
+    pub fn new(inner: W) -> Self {
+        Self::with_config(inner, Config::STANDARD)
+    }
+
+    pub fn with_config(inner: W, config: Config) -> Self {
+        EncoderWriter { inner, encoder: Encoder::new(config) }
+    }
+
+    pub fn get_ref(&self) -> &W {
+        &self.inner
+    }
+
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+
+    fn poll_write_out(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let inner = &mut self.inner;
+        self.encoder.poll_write_out(|buf| Pin::new(&mut *inner).poll_write(cx, buf))
+    }
+}
+
+impl<W: AsyncWrite + Unpin> AsyncWrite for EncoderWriter<W> {
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        ready!(this.poll_write_out(cx))?;
+        Poll::Ready(this.encoder.encode(buf))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        ready!(this.poll_write_out(cx))?;
+        Pin::new(&mut this.inner).poll_flush(cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        this.encoder.finish();
+        ready!(this.poll_write_out(cx))?;
+        Pin::new(&mut this.inner).poll_close(cx)
+    }
+}
+
+/// Decodes base64 read from the inner reader.
+///
+/// Invalid base64 is an `InvalidData` error with [`DecodeError`](crate::DecodeError)
+/// inside, its offset is from the start of the stream.
+pub struct DecoderReader<R> {
+    inner: R,
+    decoder: Decoder,
+}
+
+impl<R: AsyncRead + Unpin> DecoderReader<R> {
+    pub fn new(inner: R) -> Self {
+        Self::with_config(inner, Config::STANDARD)
+    }
+
+    pub fn with_config(inner: R, config: Config) -> Self {
+        DecoderReader { inner, decoder: Decoder::new(config) }
+    }
+
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R: AsyncRead + Unpin> AsyncRead for DecoderReader<R> {
+    fn poll_read(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut [u8]) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+
+        while this.decoder.decoded().is_empty() && !this.decoder.is_done() {
+            let inner = &mut this.inner;
+            ready!(this.decoder.poll_fill(|input| Pin::new(&mut *inner).poll_read(cx, input)))?;
+        }
+
+        let decoded = this.decoder.decoded();
+        let len = usize::min(decoded.len(), buf.len());
+        buf[..len].copy_from_slice(&decoded[..len]);
+        this.decoder.consume(len);
+
+        Poll::Ready(Ok(len))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::executor::block_on;
+    use futures::io::{AsyncReadExt, AsyncWriteExt, Cursor};
+    use futures::stream::{self, TryStreamExt};
+
+    use super::*;
+    use crate::common::test_data;
+    use crate::{base64_simd, DecodeError};
+
+    #[test]
+    fn test_encoder() {
+        block_on(async {
+            for len in [0, 1, 2, 3, 100, 20_000] {
+                let data = test_data(len);
+
+                let mut writer = EncoderWriter::new(Cursor::new(Vec::new()));
+                for piece in data.chunks(7) {
+                    writer.write_all(piece).await.unwrap();
+                }
+                writer.flush().await.unwrap();
+                let flushed = writer.get_ref().get_ref().len();
+                assert_eq!(flushed, data.len() / 3 * 4);
+
+                writer.close().await.unwrap();
+                let encoded = writer.into_inner().into_inner();
+                assert_eq!(encoded, base64_simd::encode_to_string::<16>(&data).as_bytes());
+            }
+        });
+    }
+
+    #[test]
+    fn test_decoder() {
+        block_on(async {
+            for len in [0, 1, 2, 3, 100, 20_000] {
+                let data = test_data(len);
+                let encoded = base64_simd::encode_to_string::<16>(&data).into_bytes();
+
+                // small reads, so groups and padding are split between them
+                let pieces = encoded.chunks(5).map(|piece| Ok::<_, io::Error>(piece.to_vec()));
+                let reader = stream::iter(pieces.collect::<Vec<_>>()).into_async_read();
+
+                let mut decoded = Vec::new();
+                DecoderReader::new(reader).read_to_end(&mut decoded).await.unwrap();
+                assert_eq!(decoded, data);
+            }
+
+            let err = DecoderReader::new(&b"SGVs=G8="[..]).read_to_end(&mut Vec::new()).await.unwrap_err();
+            let err = err.into_inner().unwrap().downcast::<DecodeError>().unwrap();
+            assert_eq!(*err, DecodeError::InvalidByte { offset: 4, byte: b'=' });
+        });
+    }
+}
@@ -0,0 +1,185 @@
+//! JWS compact serialization (RFC 7515, section 7.1):
+//! `BASE64URL(header).BASE64URL(payload).BASE64URL(signature)` without padding.
+//! JWTs are JWS tokens with a JSON payload.
+
+use std::fmt;
+use std::ops::Range;
+
+use crate::base64_simd;
+use crate::{Config, DecodeError};
+
+const LANES: usize = 16;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Segment {
+    Header,
+    Payload,
+    Signature,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    // A token has exactly 3 segments separated by '.'
+    SegmentCount(usize),
+    // `len % 4 == 1` is impossible for base64 without padding
+    InvalidLength { segment: Segment, len: usize },
+    // Offset is from the start of the token
+    InvalidBase64 { segment: Segment, error: DecodeError },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::SegmentCount(count) => write!(f, "expected 3 segments, found {count}"),
+            Error::InvalidLength { segment, len } => write!(f, "{segment:?}: invalid base64 length {len}"),
+            Error::InvalidBase64 { segment, error } => write!(f, "{segment:?}: {error}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::InvalidBase64 { error, .. } => Some(error),
+            _ => None,
+        }
+    }
+}
+
+// Decoded segments, borrowed from the token and the decoding buffer
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Decoded<'a> {
+    pub header: &'a [u8],
+    pub payload: &'a [u8],
+    pub signature: &'a [u8],
+    // `header.payload` as it is in the token, this is what the signature is computed over
+    pub signing_input: &'a str,
+}
+
+/// Splits a token and decodes its segments into `buffer`.
+///
+/// The buffer is cleared, so the same one can be reused for every token
+/// without new allocations.
+pub fn decode<'a>(token: &'a str, buffer: &'a mut Vec<u8>) -> Result<Decoded<'a>, Error> {
+    let mut dots = token.match_indices('.').map(|(i, _)| i);
+    let (Some(first), Some(second), None) = (dots.next(), dots.next(), dots.next()) else {
+        return Err(Error::SegmentCount(token.split('.').count()));
+    };
+
+    buffer.clear();
+    buffer.reserve(base64_simd::decoded_len(token.len()));
+
+    let header = decode_segment(token, 0..first, Segment::Header, buffer)?;
+    let payload = decode_segment(token, first + 1..second, Segment::Payload, buffer)?;
+    let signature = decode_segment(token, second + 1..token.len(), Segment::Signature, buffer)?;
+
+    let buffer = &buffer[..];
+    Ok(Decoded {
+        header: &buffer[header],
+        payload: &buffer[payload],
+        signature: &buffer[signature],
+        signing_input: &token[..second],
+    })
+}
+
+// Returns the range of the decoded segment in the buffer
+fn decode_segment(
+    token: &str,
+    range: Range<usize>,
+    segment: Segment,
+    buffer: &mut Vec<u8>,
+) -> Result<Range<usize>, Error> {
+    let start = range.start;
+    let encoded = &token.as_bytes()[range];
+    if encoded.len() % 4 == 1 {
+        return Err(Error::InvalidLength { segment, len: encoded.len() });
+    }
+
+    let decoded_start = buffer.len();
+    base64_simd::decode_with::<LANES>(encoded, buffer, Config::URL_SAFE_NO_PAD)
+        .map_err(|error| Error::InvalidBase64 { segment, error: error.add_offset(start) })?;
+
+    Ok(decoded_start..buffer.len())
+}
+
+/// Writes `BASE64URL(header).BASE64URL(payload)`, the input for signing.
+/// The token is completed by [`append_signature`].
+pub fn signing_input(header: &[u8], payload: &[u8], out: &mut String) {
+    let len = base64_simd::encoded_len(header.len()) + 1 + base64_simd::encoded_len(payload.len());
+
+    out.reserve(len);
+
+    base64_simd::encode_append_string_with::<LANES>(header, out, Config::URL_SAFE_NO_PAD);
+    out.push('.');
+    base64_simd::encode_append_string_with::<LANES>(payload, out, Config::URL_SAFE_NO_PAD);
+}
+
+/// Appends `.BASE64URL(signature)` to the signing input.
+pub fn append_signature(signature: &[u8], out: &mut String) {
+    out.reserve(1 + base64_simd::encoded_len(signature.len()));
+
+    out.push('.');
+    base64_simd::encode_append_string_with::<LANES>(signature, out, Config::URL_SAFE_NO_PAD);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // RFC 7515, appendix A.1
+    const TOKEN: &str = "\
+eyJ0eXAiOiJKV1QiLA0KICJhbGciOiJIUzI1NiJ9\
+.eyJpc3MiOiJqb2UiLA0KICJleHAiOjEzMDA4MTkzODAsDQogImh0dHA6Ly9leGFtcGxlLmNvbS9pc19yb290Ijp0cnVlfQ\
+.dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXk";
+
+    #[test]
+    fn test_decode() {
+        let mut buffer = Vec::new();
+        let decoded = decode(TOKEN, &mut buffer).unwrap();
+
+        assert_eq!(decoded.header, b"{\"typ\":\"JWT\",\r\n \"alg\":\"HS256\"}");
+        assert!(decoded.payload.starts_with(b"{\"iss\":\"joe\""));
+        assert_eq!(decoded.signature.len(), 32);
+        assert_eq!(decoded.signature[..4], [116, 24, 223, 180]);
+        assert_eq!(decoded.signing_input, &TOKEN[..TOKEN.rfind('.').unwrap()]);
+    }
+
+    #[test]
+    fn test_encode() {
+        let mut buffer = Vec::new();
+        let decoded = decode(TOKEN, &mut buffer).unwrap();
+
+        let mut token = String::new();
+        signing_input(decoded.header, decoded.payload, &mut token);
+        assert_eq!(token, decoded.signing_input);
+
+        append_signature(decoded.signature, &mut token);
+        assert_eq!(token, TOKEN);
+    }
+
+    #[test]
+    fn test_errors() {
+        let mut buffer = Vec::new();
+
+        assert_eq!(decode("a.b", &mut buffer), Err(Error::SegmentCount(2)));
+        assert_eq!(decode("a.b.c.d", &mut buffer), Err(Error::SegmentCount(4)));
+        assert_eq!(
+            decode("eyJ0.eyJpc3Mi.a", &mut buffer),
+            Err(Error::InvalidLength { segment: Segment::Signature, len: 1 })
+        );
+        assert_eq!(
+            decode("eyJ0.eyJp+3Mi.", &mut buffer),
+            Err(Error::InvalidBase64 {
+                segment: Segment::Payload,
+                error: DecodeError::InvalidByte { offset: 9, byte: b'+' },
+            })
+        );
+        assert_eq!(
+            decode("eyJ0eQ==.eyJpc3Mi.", &mut buffer),
+            Err(Error::InvalidBase64 {
+                segment: Segment::Header,
+                error: DecodeError::InvalidByte { offset: 6, byte: b'=' },
+            })
+        );
+    }
+}
@@ -1,5 +1,39 @@
 #![feature(portable_simd)]
 
+pub mod armor;
+#[cfg(target_arch = "x86_64")]
+pub mod avx2;
+#[cfg(target_arch = "x86_64")]
+pub mod avx512vbmi;
 pub mod base64;
 pub mod base64_simd;
-mod common;
\ No newline at end of file
+pub mod basic_auth;
+#[cfg(feature = "bytes")]
+pub mod bytes;
+pub mod data_uri;
+pub mod display;
+pub mod encoded_word;
+pub mod engine;
+#[cfg(feature = "futures-io")]
+pub mod futures_io;
+pub mod jws;
+#[cfg(any(target_arch = "aarch64", test))]
+pub mod neon;
+pub mod parallel;
+pub mod pem;
+#[cfg(feature = "serde")]
+pub mod serde;
+pub mod swar;
+#[cfg(feature = "tokio")]
+pub mod tokio;
+pub mod websocket;
+mod common;
+mod config;
+mod error;
+#[cfg(any(feature = "tokio", feature = "futures-io"))]
+mod stream;
+
+pub use config::{Alphabet, Config};
+pub use display::{b64, Base64Display};
+pub use engine::{AutoEngine, Engine, ScalarEngine, SimdEngine};
+pub use error::DecodeError;
\ No newline at end of file
@@ -0,0 +1,448 @@
+//! Encoding and decoding with NEON on AArch64, e.g. on Graviton.
+//!
+//! `vld3`/`vst4` split 48 bytes into 3 registers and put 4 registers of chars
+//! back together, so 3 bytes become 4 sextets with plain shifts. `vqtbl4`
+//! looks up in 64 bytes at once, the alphabet is a single table.
+//!
+//! The kernels are written over `Ops`, so on other targets the tests run
+//! them with a `std::simd` model of the instructions.
+
+use crate::base64_simd::{decoded_len, encoded_len, padded_encoded_len};
+use crate::common;
+use crate::{Alphabet, Config, DecodeError};
+
+const LANES: usize = 16;
+
+// Bytes of input and chars of output of one step
+const CHUNK_SIZE: usize = 3 * LANES;
+const ENCODED_SIZE: usize = 4 * LANES;
+
+#[cfg(target_arch = "aarch64")]
+type Vector = std::arch::aarch64::uint8x16_t;
+#[cfg(not(target_arch = "aarch64"))]
+type Vector = std::simd::Simd<u8, LANES>;
+
+pub fn encode(data: &[u8], out: &mut Vec<u8>) {
+    encode_with(data, out, Config::STANDARD)
+}
+
+pub fn encode_with(data: &[u8], out: &mut Vec<u8>, config: Config) {
+    let final_size = match config.padding {
+        true => padded_encoded_len(data.len()),
+        false => encoded_len(data.len()),
+    };
+    out.reserve(final_size);
+
+    // Safety: there was allocated enough space
+    unsafe {
+        let written = encode_to_ptr::<Vector>(data, out.as_mut_ptr().add(out.len()), config.alphabet);
+        out.set_len(out.len() + written);
+    }
+
+    // padding
+    if config.padding {
+        common::pad_with_trailing_eq(data.len(), out);
+    }
+}
+
+pub fn decode(data: &[u8], out: &mut Vec<u8>) -> Result<(), DecodeError> {
+    decode_with(data, out, Config::STANDARD)
+}
+
+pub fn decode_with(data: &[u8], out: &mut Vec<u8>, config: Config) -> Result<(), DecodeError> {
+    // without padding '=' is an invalid byte
+    let data = if config.padding { common::remove_trailing_eq(data) } else { data };
+    out.reserve(decoded_len(data.len()));
+
+    // Safety: there was allocated enough space
+    unsafe {
+        let written = decode_to_ptr::<Vector>(data, out.as_mut_ptr().add(out.len()), config.alphabet)?;
+        out.set_len(out.len() + written);
+    }
+
+    Ok(())
+}
+
+/// Instructions used by the kernels, one implementation per target.
+pub(crate) trait Ops: Copy {
+    /// 4 registers, the table of `lookup()`
+    type Table: Copy;
+
+    fn splat(byte: u8) -> Self;
+    /// Shifts left, or right by a negative `n` (`vshl`)
+    fn shift(self, n: i8) -> Self;
+    fn or(self, other: Self) -> Self;
+    fn and(self, other: Self) -> Self;
+    fn wrapping_sub(self, other: Self) -> Self;
+    /// Whether bit 7 is set in any lane
+    fn any_high_bit(self) -> bool;
+
+    fn table(bytes: &[u8; 64]) -> Self::Table;
+    /// Byte of `table` at every index, 0 if it is out of the table (`vqtbl4`)
+    fn lookup(table: Self::Table, indices: Self) -> Self;
+
+    /// Every 3rd byte to the same register (`vld3`)
+    ///
+    /// Safety: `ptr` has to be valid for reads of 48 bytes.
+    unsafe fn load3(ptr: *const u8) -> [Self; 3];
+    /// Safety: `ptr` has to be valid for reads of 64 bytes.
+    unsafe fn load4(ptr: *const u8) -> [Self; 4];
+    /// Safety: `ptr` has to be valid for writes of 48 bytes.
+    unsafe fn store3(ptr: *mut u8, vectors: [Self; 3]);
+    /// Lanes of the registers one after another (`vst4`)
+    ///
+    /// Safety: `ptr` has to be valid for writes of 64 bytes.
+    unsafe fn store4(ptr: *mut u8, vectors: [Self; 4]);
+}
+
+// Writes exactly encoded_len(data.len()) bytes, without padding.
+//
+// Safety: `out` has to be valid for writes of that many bytes.
+unsafe fn encode_to_ptr<V: Ops>(data: &[u8], out: *mut u8, alphabet: Alphabet) -> usize {
+    let table = V::table(alphabet.chars());
+    let mut ptr = out;
+
+    let mut chunks = data.chunks_exact(CHUNK_SIZE);
+
+    // main loop
+    for chunk in &mut chunks {
+        V::store4(ptr, encode_hot(V::load3(chunk.as_ptr()), table));
+        ptr = ptr.add(ENCODED_SIZE);
+    }
+
+    // remainder, it goes through a copy
+    let rest = chunks.remainder();
+    if !rest.is_empty() {
+        let mut bytes = [0u8; CHUNK_SIZE];
+        bytes[..rest.len()].copy_from_slice(rest);
+
+        let mut encoded = [0u8; ENCODED_SIZE];
+        V::store4(encoded.as_mut_ptr(), encode_hot(V::load3(bytes.as_ptr()), table));
+
+        let len = encoded_len(rest.len());
+        std::ptr::copy_nonoverlapping(encoded.as_ptr(), ptr, len);
+        ptr = ptr.add(len);
+    }
+
+    ptr.offset_from(out) as usize
+}
+
+// Writes exactly decoded_len(data.len()) bytes, `data` is without padding.
+//
+// Safety: `out` has to be valid for writes of that many bytes.
+unsafe fn decode_to_ptr<V: Ops>(data: &[u8], out: *mut u8, alphabet: Alphabet) -> Result<usize, DecodeError> {
+    let [lut_lo, lut_hi] = split_lut(alphabet.decode_lut());
+    let (lut_lo, lut_hi) = (V::table(lut_lo), V::table(lut_hi));
+    let mut ptr = out;
+    let mut invalid = V::splat(0);
+
+    let mut chunks = data.chunks_exact(ENCODED_SIZE);
+
+    // main loop
+    for chunk in &mut chunks {
+        let (bytes, errors) = decode_hot(V::load4(chunk.as_ptr()), lut_lo, lut_hi);
+        invalid = invalid.or(errors);
+
+        V::store3(ptr, bytes);
+        ptr = ptr.add(CHUNK_SIZE);
+    }
+
+    // remainder, filled with 'A'
+    let rest = chunks.remainder();
+    if !rest.is_empty() {
+        let mut ascii = [b'A'; ENCODED_SIZE];
+        ascii[..rest.len()].copy_from_slice(rest);
+
+        let (bytes, errors) = decode_hot(V::load4(ascii.as_ptr()), lut_lo, lut_hi);
+        invalid = invalid.or(errors);
+
+        let mut decoded = [0u8; CHUNK_SIZE];
+        V::store3(decoded.as_mut_ptr(), bytes);
+
+        let len = decoded_len(rest.len());
+        std::ptr::copy_nonoverlapping(decoded.as_ptr(), ptr, len);
+        ptr = ptr.add(len);
+    }
+
+    if invalid.any_high_bit() {
+        return Err(common::find_invalid(data, alphabet, false));
+    }
+
+    Ok(ptr.offset_from(out) as usize)
+}
+
+fn split_lut(lut: &[u8; 128]) -> [&[u8; 64]; 2] {
+    let (lo, hi) = lut.split_at(64);
+    [lo.try_into().unwrap(), hi.try_into().unwrap()]
+}
+
+// 3 registers of bytes (every 3rd byte in each) into 4 registers of chars
+#[inline]
+fn encode_hot<V: Ops>([a, b, c]: [V; 3], table: V::Table) -> [V; 4] {
+    // aaaaaabb bbbbcccc ccdddddd
+    let mask = V::splat(0x3f);
+    let sextets = [
+        a.shift(-2),
+        a.shift(4).or(b.shift(-4)).and(mask),
+        b.shift(2).or(c.shift(-6)).and(mask),
+        c.and(mask),
+    ];
+
+    sextets.map(|sextets| V::lookup(table, sextets))
+}
+
+// 4 registers of chars into 3 registers of bytes, and bit 7 set for invalid chars
+#[inline]
+fn decode_hot<V: Ops>(ascii: [V; 4], lut_lo: V::Table, lut_hi: V::Table) -> ([V; 3], V) {
+    // Both halves of the 128-entry table, an index is out of one of them.
+    // Chars out of the alphabet are 0x80 in the table, non-ASCII chars
+    // are out of both, but have bit 7 set themselves
+    let sextets = ascii.map(|ascii| {
+        V::lookup(lut_lo, ascii).or(V::lookup(lut_hi, ascii.wrapping_sub(V::splat(64))))
+    });
+    let invalid = (0..4).fold(V::splat(0), |invalid, i| invalid.or(sextets[i]).or(ascii[i]));
+
+    // 00aaaaaa 00bbbbbb 00cccccc 00dddddd => aaaaaabb bbbbcccc ccdddddd
+    let [a, b, c, d] = sextets;
+    let bytes = [
+        a.shift(2).or(b.shift(-4)),
+        b.shift(4).or(c.shift(-2)),
+        c.shift(6).or(d),
+    ];
+
+    (bytes, invalid)
+}
+
+#[cfg(target_arch = "aarch64")]
+mod arch {
+    use std::arch::aarch64::*;
+
+    use super::Ops;
+
+    // NEON is always enabled on AArch64, newer compilers allow the arithmetic
+    // intrinsics outside of unsafe blocks
+    #[allow(unused_unsafe)]
+    impl Ops for uint8x16_t {
+        type Table = uint8x16x4_t;
+
+        #[inline]
+        fn splat(byte: u8) -> Self {
+            unsafe { vdupq_n_u8(byte) }
+        }
+
+        #[inline]
+        fn shift(self, n: i8) -> Self {
+            unsafe { vshlq_u8(self, vdupq_n_s8(n)) }
+        }
+
+        #[inline]
+        fn or(self, other: Self) -> Self {
+            unsafe { vorrq_u8(self, other) }
+        }
+
+        #[inline]
+        fn and(self, other: Self) -> Self {
+            unsafe { vandq_u8(self, other) }
+        }
+
+        #[inline]
+        fn wrapping_sub(self, other: Self) -> Self {
+            unsafe { vsubq_u8(self, other) }
+        }
+
+        #[inline]
+        fn any_high_bit(self) -> bool {
+            unsafe { vmaxvq_u8(self) >= 0x80 }
+        }
+
+        #[inline]
+        fn table(bytes: &[u8; 64]) -> Self::Table {
+            // Safety: the array has 64 bytes
+            unsafe { vld1q_u8_x4(bytes.as_ptr()) }
+        }
+
+        #[inline]
+        fn lookup(table: Self::Table, indices: Self) -> Self {
+            unsafe { vqtbl4q_u8(table, indices) }
+        }
+
+        #[inline]
+        unsafe fn load3(ptr: *const u8) -> [Self; 3] {
+            let uint8x16x3_t(a, b, c) = vld3q_u8(ptr);
+            [a, b, c]
+        }
+
+        #[inline]
+        unsafe fn load4(ptr: *const u8) -> [Self; 4] {
+            let uint8x16x4_t(a, b, c, d) = vld4q_u8(ptr);
+            [a, b, c, d]
+        }
+
+        #[inline]
+        unsafe fn store3(ptr: *mut u8, [a, b, c]: [Self; 3]) {
+            vst3q_u8(ptr, uint8x16x3_t(a, b, c))
+        }
+
+        #[inline]
+        unsafe fn store4(ptr: *mut u8, [a, b, c, d]: [Self; 4]) {
+            vst4q_u8(ptr, uint8x16x4_t(a, b, c, d))
+        }
+    }
+}
+
+// The same semantics as the instructions, for tests on other targets
+#[cfg(not(target_arch = "aarch64"))]
+mod model {
+    use std::simd::num::SimdUint;
+    use std::simd::Simd;
+
+    use super::{Ops, LANES};
+
+    impl Ops for Simd<u8, LANES> {
+        type Table = [u8; 64];
+
+        fn splat(byte: u8) -> Self {
+            Simd::splat(byte)
+        }
+
+        fn shift(self, n: i8) -> Self {
+            match n >= 0 {
+                true => self << Simd::splat(n as u8),
+                false => self >> Simd::splat(n.unsigned_abs()),
+            }
+        }
+
+        fn or(self, other: Self) -> Self {
+            self | other
+        }
+
+        fn and(self, other: Self) -> Self {
+            self & other
+        }
+
+        fn wrapping_sub(self, other: Self) -> Self {
+            self - other
+        }
+
+        fn any_high_bit(self) -> bool {
+            self.reduce_max() >= 0x80
+        }
+
+        fn table(bytes: &[u8; 64]) -> Self::Table {
+            *bytes
+        }
+
+        fn lookup(table: Self::Table, indices: Self) -> Self {
+            Simd::from_array(indices.to_array().map(|i| table.get(i as usize).copied().unwrap_or(0)))
+        }
+
+        unsafe fn load3(ptr: *const u8) -> [Self; 3] {
+            std::array::from_fn(|j| Simd::from_array(std::array::from_fn(|i| *ptr.add(3 * i + j))))
+        }
+
+        unsafe fn load4(ptr: *const u8) -> [Self; 4] {
+            std::array::from_fn(|j| Simd::from_array(std::array::from_fn(|i| *ptr.add(4 * i + j))))
+        }
+
+        unsafe fn store3(ptr: *mut u8, vectors: [Self; 3]) {
+            for (j, vector) in vectors.iter().enumerate() {
+                for (i, &byte) in vector.as_array().iter().enumerate() {
+                    *ptr.add(3 * i + j) = byte;
+                }
+            }
+        }
+
+        unsafe fn store4(ptr: *mut u8, vectors: [Self; 4]) {
+            for (j, vector) in vectors.iter().enumerate() {
+                for (i, &byte) in vector.as_array().iter().enumerate() {
+                    *ptr.add(4 * i + j) = byte;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::simd::Simd;
+
+    use super::*;
+    use crate::{base64, base64_simd};
+    use crate::common::{check_round_trip, test_data, test_vectors};
+
+    const ALPHABETS: [Alphabet; 2] = [Alphabet::Standard, Alphabet::UrlSafe];
+
+    // The portable kernels take bytes in order, NEON ones every 3rd or 4th
+    #[test]
+    fn test_kernels() {
+        for alphabet in ALPHABETS {
+            for bytes in test_vectors::<64>() {
+
+                let mut encoded = [0u8; 64];
+                // Safety: both arrays are long enough
+                unsafe {
+                    let asciis = encode_hot(Vector::load3(bytes.as_ptr()), Vector::table(alphabet.chars()));
+                    Vector::store4(encoded.as_mut_ptr(), asciis);
+                }
+                let expected = base64_simd::encode_hot(Simd::<u8, 64>::from(bytes), alphabet);
+                assert_eq!(encoded, *expected.as_array());
+
+                // valid chars and any bytes
+                let chars = bytes.map(|b| alphabet.chars()[b as usize % 64]);
+                for ascii in [chars, bytes] {
+                    let (expected, ok) = base64_simd::decode_hot(Simd::<u8, 64>::from(ascii), alphabet);
+                    let [lo, hi] = split_lut(alphabet.decode_lut());
+                    let mut decoded = [0u8; 48];
+                    // Safety: both arrays are long enough
+                    let invalid = unsafe {
+                        let (bytes, invalid) = decode_hot(Vector::load4(ascii.as_ptr()), Vector::table(lo), Vector::table(hi));
+                        Vector::store3(decoded.as_mut_ptr(), bytes);
+                        invalid
+                    };
+                    assert_eq!(!invalid.any_high_bit(), ok);
+                    if ok {
+                        assert_eq!(decoded, expected.as_array()[..48]);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_encode_decode() {
+        // around the 48 bytes of vld3 and the 64 chars of vld4, the rest goes through a copy
+        for len in [0, 1, 2, 3, 47, 48, 49, 95, 96, 97, 143, 144, 145, 1000, 10_000] {
+            for config in [Config::STANDARD, Config::URL_SAFE_NO_PAD] {
+                check_round_trip(&test_data(len), config, encode_with, decode_with);
+            }
+        }
+    }
+
+    // vqtbl4 gives 0 for indices out of its 64 bytes, every byte is looked up
+    // in both halves of the table and has to be out of at least one of them
+    #[test]
+    fn test_errors() {
+        for config in [Config::STANDARD_NO_PAD, Config::URL_SAFE_NO_PAD] {
+            // 2 steps of the main loop and 8 chars of the remainder
+            let mut encoded = Vec::new();
+            base64::encode_with(&test_data(102), &mut encoded, config);
+
+            for byte in 0..=255 {
+                // every register of vld4 and the remainder
+                for offset in [0, 1, 2, 3, 66, 127, 130] {
+                    let mut invalid = encoded.clone();
+                    invalid[offset] = byte;
+
+                    let mut out = vec![1];
+                    let result = decode_with(&invalid, &mut out, config);
+                    if config.alphabet.contains(byte) {
+                        assert!(result.is_ok(), "{byte:#x} at {offset}");
+                    } else {
+                        assert_eq!(result, Err(DecodeError::InvalidByte { offset, byte }));
+                        assert_eq!(out, [1]);
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,186 @@
+//! Encoding and decoding of large buffers on several threads.
+//!
+//! 3 bytes of input are always 4 chars of output, so the input is split
+//! into pieces at multiples of 3 (encoding) or 4 (decoding) and every
+//! thread writes its piece into its own part of the output.
+
+use std::mem::MaybeUninit;
+use std::num::NonZeroUsize;
+use std::simd::{LaneCount, SupportedLaneCount};
+use std::thread;
+
+use crate::base64_simd::{self, decoded_len, encoded_len, padded_encoded_len};
+use crate::common;
+use crate::{Config, DecodeError};
+
+/// Inputs shorter than this are processed on the calling thread,
+/// starting threads costs more than they save.
+pub const PAR_THRESHOLD: usize = 1 << 20;
+
+// Every thread gets at least this much input
+const MIN_PIECE: usize = PAR_THRESHOLD / 4;
+
+pub fn par_encode<const N: usize>(data: &[u8], out: &mut Vec<u8>)
+where
+    LaneCount<N>: SupportedLaneCount
+{
+    par_encode_with::<N>(data, out, Config::STANDARD)
+}
+
+pub fn par_encode_with<const N: usize>(data: &[u8], out: &mut Vec<u8>, config: Config)
+where
+    LaneCount<N>: SupportedLaneCount
+{
+    // only the last piece can have a remainder, so whole vectors in every other
+    let piece_len = piece_len(data.len(), 3 * N, threads());
+    if piece_len >= data.len() {
+        return base64_simd::encode_with::<N>(data, out, config);
+    }
+
+    let final_size = match config.padding {
+        true => padded_encoded_len(data.len()),
+        false => encoded_len(data.len()),
+    };
+    out.reserve(final_size);
+    let output = &mut out.spare_capacity_mut()[..final_size];
+
+    thread::scope(|scope| {
+        let pieces = data.chunks(piece_len);
+        let outputs = output.chunks_mut(piece_len / 3 * 4);
+        for (piece, output) in pieces.zip(outputs) {
+            scope.spawn(move || {
+                // Safety: the output of a piece is its encoded length, and the padding for the last one
+                let written = unsafe {
+                    base64_simd::encode_to_ptr_with::<N>(piece, output.as_mut_ptr().cast(), config.alphabet)
+                };
+                output[written..].fill(MaybeUninit::new(b'='));
+            });
+        }
+    });
+
+    // Safety: the threads have written the whole output
+    unsafe {
+        out.set_len(out.len() + final_size);
+    }
+}
+
+pub fn par_decode<const N: usize>(data: &[u8], out: &mut Vec<u8>) -> Result<(), DecodeError>
+where
+    LaneCount<N>: SupportedLaneCount
+{
+    par_decode_with::<N>(data, out, Config::STANDARD)
+}
+
+/// On error `out` is left as it was, the offset is from the start of `data`.
+pub fn par_decode_with<const N: usize>(data: &[u8], out: &mut Vec<u8>, config: Config) -> Result<(), DecodeError>
+where
+    LaneCount<N>: SupportedLaneCount
+{
+    let piece_len = piece_len(data.len(), 4 * N, threads());
+    if piece_len >= data.len() {
+        return base64_simd::decode_with::<N>(data, out, config);
+    }
+
+    let unpadded = if config.padding { common::remove_trailing_eq(data) } else { data };
+    let final_size = decoded_len(unpadded.len());
+    out.reserve(final_size);
+    let output = &mut out.spare_capacity_mut()[..final_size];
+
+    let result = thread::scope(|scope| {
+        // padding can be only at the end, so '=' is an invalid byte in the pieces
+        let pieces = unpadded.chunks(piece_len);
+        let outputs = output.chunks_mut(piece_len / 4 * 3);
+        let threads = pieces.zip(outputs).enumerate().map(|(i, (piece, output))| {
+            scope.spawn(move || {
+                // Safety: the output of a piece is exactly its decoded length
+                unsafe {
+                    base64_simd::decode_to_ptr::<N>(piece, output.as_mut_ptr().cast(), output.len(), config.alphabet)
+                }
+                .map_err(|err| err.add_offset(i * piece_len))
+            })
+        });
+
+        // the first error in the input is reported
+        threads.collect::<Vec<_>>().into_iter().try_for_each(|thread| {
+            thread.join().unwrap().map(drop)
+        })
+    });
+    result?;
+
+    // Safety: the threads have written the whole output without errors
+    unsafe {
+        out.set_len(out.len() + final_size);
+    }
+
+    Ok(())
+}
+
+fn threads() -> usize {
+    thread::available_parallelism().map_or(1, NonZeroUsize::get)
+}
+
+// Input length of one thread, a multiple of `align`.
+// It's the whole input if there is no need for threads.
+fn piece_len(len: usize, align: usize, threads: usize) -> usize {
+    if len < PAR_THRESHOLD || threads == 1 {
+        return len;
+    }
+
+    let piece_len = usize::max(len.div_ceil(threads), MIN_PIECE);
+    piece_len.next_multiple_of(align)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::test_data;
+
+    #[test]
+    fn test_piece_len() {
+        assert_eq!(piece_len(PAR_THRESHOLD - 1, 48, 8), PAR_THRESHOLD - 1);
+        assert_eq!(piece_len(10 * PAR_THRESHOLD, 48, 1), 10 * PAR_THRESHOLD);
+
+        let len = 10 * PAR_THRESHOLD + 1;
+        assert_eq!(piece_len(len, 48, 8), len.div_ceil(8).next_multiple_of(48));
+        assert_eq!(piece_len(len, 64, 1000), MIN_PIECE);
+    }
+
+    #[test]
+    fn test_encode_decode() {
+        for len in [0, 100, PAR_THRESHOLD - 1, PAR_THRESHOLD, PAR_THRESHOLD + 1, PAR_THRESHOLD + 2] {
+            let data = test_data(len);
+            for config in [Config::STANDARD, Config::URL_SAFE_NO_PAD] {
+                let mut expected = Vec::new();
+                base64_simd::encode_with::<16>(&data, &mut expected, config);
+
+                let mut encoded = b"prefix".to_vec();
+                par_encode_with::<16>(&data, &mut encoded, config);
+                assert!(encoded[6..] == expected);
+
+                let mut decoded = b"prefix".to_vec();
+                par_decode_with::<16>(&encoded[6..], &mut decoded, config).unwrap();
+                assert!(decoded[6..] == data);
+            }
+        }
+    }
+
+    #[test]
+    fn test_errors() {
+        let mut encoded = Vec::new();
+        par_encode::<32>(&test_data(PAR_THRESHOLD), &mut encoded);
+
+        // in the last piece, in the middle and in the other pieces
+        for offset in [encoded.len() - 5, encoded.len() / 2 + 3, 1] {
+            let mut invalid = encoded.clone();
+            invalid[offset] = b'=';
+            invalid[offset + 1..].fill(b'*');
+
+            let mut decoded = vec![1];
+            assert_eq!(
+                par_decode::<32>(&invalid, &mut decoded),
+                Err(DecodeError::InvalidByte { offset, byte: b'=' })
+            );
+            assert_eq!(decoded, [1]);
+        }
+    }
+}
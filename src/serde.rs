@@ -0,0 +1,154 @@
+//! Byte fields as base64 strings, for `#[serde(with = "simd_base64::serde::standard")]`.
+//!
+//! There is a module for every alphabet and padding, each works for
+//! `Vec<u8>` and `[u8; N]` fields. Strings are decoded as they are borrowed
+//! from the deserializer and encoded straight into the serializer.
+
+use std::fmt;
+use std::marker::PhantomData;
+
+use ::serde::de::{self, Deserializer, Visitor};
+use ::serde::Serializer;
+
+use crate::base64_simd;
+use crate::common;
+use crate::{Base64Display, Config};
+
+const LANES: usize = 32;
+
+/// Types which a base64 string can be deserialized into.
+pub trait FromBase64: Sized {
+    fn from_base64<E: de::Error>(data: &[u8], config: Config) -> Result<Self, E>;
+}
+
+impl FromBase64 for Vec<u8> {
+    fn from_base64<E: de::Error>(data: &[u8], config: Config) -> Result<Self, E> {
+        let mut out = Vec::new();
+        base64_simd::decode_with::<LANES>(data, &mut out, config).map_err(E::custom)?;
+        Ok(out)
+    }
+}
+
+impl<const N: usize> FromBase64 for [u8; N] {
+    fn from_base64<E: de::Error>(data: &[u8], config: Config) -> Result<Self, E> {
+        let unpadded = if config.padding { common::remove_trailing_eq(data) } else { data };
+        let len = base64_simd::decoded_len(unpadded.len());
+        if len != N {
+            return Err(E::custom(format_args!("expected base64 of {N} bytes, found {len} bytes")));
+        }
+
+        let mut out = [0; N];
+        base64_simd::decode_slice_with::<LANES>(data, &mut out, config).map_err(E::custom)?;
+        Ok(out)
+    }
+}
+
+fn serialize<S: Serializer>(bytes: &[u8], serializer: S, config: Config) -> Result<S::Ok, S::Error> {
+    serializer.collect_str(&Base64Display::with_config(bytes, config))
+}
+
+fn deserialize<'de, T: FromBase64, D: Deserializer<'de>>(deserializer: D, config: Config) -> Result<T, D::Error> {
+    deserializer.deserialize_str(Base64Visitor { config, output: PhantomData })
+}
+
+struct Base64Visitor<T> {
+    config: Config,
+    output: PhantomData<T>,
+}
+
+impl<T: FromBase64> Visitor<'_> for Base64Visitor<T> {
+    type Value = T;
+
+    fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "a base64 string")
+    }
+
+    fn visit_str<E: de::Error>(self, value: &str) -> Result<T, E> {
+        T::from_base64(value.as_bytes(), self.config)
+    }
+
+    // Some formats hand out strings as bytes
+    fn visit_bytes<E: de::Error>(self, value: &[u8]) -> Result<T, E> {
+        T::from_base64(value, self.config)
+    }
+}
+
+macro_rules! config_module {
+    ($name:ident, $config:expr, $doc:literal) => {
+        #[doc = $doc]
+        pub mod $name {
+            use ::serde::{Deserializer, Serializer};
+
+            use super::FromBase64;
+            use crate::Config;
+
+            pub fn serialize<T, S>(bytes: &T, serializer: S) -> Result<S::Ok, S::Error>
+            where
+                T: AsRef<[u8]> + ?Sized,
+                S: Serializer,
+            {
+                super::serialize(bytes.as_ref(), serializer, $config)
+            }
+
+            pub fn deserialize<'de, T, D>(deserializer: D) -> Result<T, D::Error>
+            where
+                T: FromBase64,
+                D: Deserializer<'de>,
+            {
+                super::deserialize(deserializer, $config)
+            }
+        }
+    };
+}
+
+config_module!(standard, Config::STANDARD, "Standard alphabet with padding.");
+config_module!(standard_no_pad, Config::STANDARD_NO_PAD, "Standard alphabet without padding.");
+config_module!(url_safe, Config::URL_SAFE, "URL-safe alphabet with padding.");
+config_module!(url_safe_no_pad, Config::URL_SAFE_NO_PAD, "URL-safe alphabet without padding.");
+
+#[cfg(test)]
+mod tests {
+    use ::serde::{Deserialize, Serialize};
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Key {
+        #[serde(with = "crate::serde::standard")]
+        data: Vec<u8>,
+        #[serde(with = "crate::serde::url_safe_no_pad")]
+        id: [u8; 4],
+        #[serde(with = "crate::serde::standard_no_pad")]
+        empty: Vec<u8>,
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let key = Key {
+            data: b"Hello, world!".to_vec(),
+            id: [0xfb, 0xff, 0xbf, 0x00],
+            empty: Vec::new(),
+        };
+
+        let json = serde_json::to_string(&key).unwrap();
+        assert_eq!(json, r#"{"data":"SGVsbG8sIHdvcmxkIQ==","id":"-_-_AA","empty":""}"#);
+        assert_eq!(serde_json::from_str::<Key>(&json).unwrap(), key);
+
+        // escapes make serde_json hand out a transient string instead of a borrowed one
+        let escaped = r#"{"data":"SGVsbG8sIHdvcmxkIQ\u003d\u003d","id":"-_-_AA","empty":""}"#;
+        assert_eq!(serde_json::from_str::<Key>(escaped).unwrap(), key);
+    }
+
+    #[test]
+    fn test_errors() {
+        let invalid = serde_json::from_str::<Key>(r#"{"data":"SGV$","id":"-_-_AA","empty":""}"#);
+        assert!(invalid.unwrap_err().to_string().contains("invalid base64 byte '$' at offset 3"));
+
+        let short = serde_json::from_str::<Key>(r#"{"data":"","id":"-_-_","empty":""}"#);
+        assert!(short.unwrap_err().to_string().contains("expected base64 of 4 bytes, found 3 bytes"));
+
+        let padded = serde_json::from_str::<Key>(r#"{"data":"","id":"-_-_AA","empty":"AA=="}"#);
+        assert!(padded.unwrap_err().to_string().contains("invalid base64 byte '=' at offset 2"));
+
+        let number = serde_json::from_str::<Key>(r#"{"data":1,"id":"-_-_AA","empty":""}"#);
+        assert!(number.unwrap_err().to_string().contains("expected a base64 string"));
+    }
+}
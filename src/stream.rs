@@ -0,0 +1,182 @@
+//! State of the streaming adapters, shared by tokio.rs and futures_io.rs.
+//! It doesn't do any I/O, the adapters feed it and drain it.
+
+use std::io;
+use std::task::{ready, Poll};
+
+use crate::base64_simd;
+use crate::Config;
+
+const LANES: usize = 32;
+
+// Input taken by one write, it's a multiple of 3, so no padding in the middle
+const MAX_ENCODE: usize = 6 * 1024;
+// Base64 read from the inner reader at once
+const READ_SIZE: usize = 8 * 1024;
+
+pub(crate) struct Encoder {
+    config: Config,
+    // Bytes which don't make a whole group of 3 yet
+    pending: [u8; 3],
+    pending_len: usize,
+    // Encoded, but not written to the inner writer yet
+    buffer: Vec<u8>,
+    written: usize,
+    finished: bool,
+}
+
+impl Encoder {
+    pub fn new(config: Config) -> Self {
+        Encoder {
+            config,
+            pending: [0; 3],
+            pending_len: 0,
+            buffer: Vec::new(),
+            written: 0,
+            finished: false,
+        }
+    }
+
+    /// Encodes a part of `data` and returns its length.
+    /// Must be called only when everything encoded before is written.
+    pub fn encode(&mut self, data: &[u8]) -> io::Result<usize> {
+        if self.finished {
+            return Err(io::Error::new(io::ErrorKind::BrokenPipe, "write after shutdown"));
+        }
+        debug_assert!(self.unwritten().is_empty());
+        self.buffer.clear();
+        self.written = 0;
+
+        // complete the pending group first
+        let mut consumed = 0;
+        if self.pending_len > 0 {
+            consumed = usize::min(3 - self.pending_len, data.len());
+            self.pending[self.pending_len..self.pending_len + consumed].copy_from_slice(&data[..consumed]);
+            self.pending_len += consumed;
+            if self.pending_len < 3 {
+                return Ok(consumed);
+            }
+            base64_simd::encode_with::<LANES>(&self.pending, &mut self.buffer, self.config);
+            self.pending_len = 0;
+        }
+
+        let rest = &data[consumed..];
+        let len = usize::min(rest.len(), MAX_ENCODE);
+        let whole = len - len % 3;
+        base64_simd::encode_with::<LANES>(&rest[..whole], &mut self.buffer, self.config);
+
+        // the tail waits for the next write, or for padding on shutdown
+        self.pending[..len - whole].copy_from_slice(&rest[whole..len]);
+        self.pending_len = len - whole;
+
+        Ok(consumed + len)
+    }
+
+    /// Encodes the pending bytes with padding, nothing can be written after it.
+    pub fn finish(&mut self) {
+        if !self.finished {
+            let pending = &self.pending[..self.pending_len];
+            base64_simd::encode_with::<LANES>(pending, &mut self.buffer, self.config);
+            self.pending_len = 0;
+            self.finished = true;
+        }
+    }
+
+    fn unwritten(&self) -> &[u8] {
+        &self.buffer[self.written..]
+    }
+
+    fn consume(&mut self, written: usize) {
+        self.written += written;
+    }
+
+    /// Writes everything encoded so far with `write`.
+    pub fn poll_write_out(&mut self, mut write: impl FnMut(&[u8]) -> Poll<io::Result<usize>>) -> Poll<io::Result<()>> {
+        while !self.unwritten().is_empty() {
+            let written = ready!(write(self.unwritten()))?;
+            if written == 0 {
+                return Poll::Ready(Err(io::ErrorKind::WriteZero.into()));
+            }
+            self.consume(written);
+        }
+        Poll::Ready(Ok(()))
+    }
+}
+
+pub(crate) struct Decoder {
+    config: Config,
+    // Read, but not decoded yet
+    input: Vec<u8>,
+    // Chars decoded before `input`, for error offsets
+    offset: usize,
+    // Decoded, but not read from the adapter yet
+    output: Vec<u8>,
+    read: usize,
+    eof: bool,
+}
+
+impl Decoder {
+    pub fn new(config: Config) -> Self {
+        Decoder {
+            config,
+            input: Vec::new(),
+            offset: 0,
+            output: Vec::new(),
+            read: 0,
+            eof: false,
+        }
+    }
+
+    pub fn decoded(&self) -> &[u8] {
+        &self.output[self.read..]
+    }
+
+    pub fn consume(&mut self, read: usize) {
+        self.read += read;
+    }
+
+    // Everything is read and decoded
+    pub fn is_done(&self) -> bool {
+        self.eof && self.input.is_empty() && self.decoded().is_empty()
+    }
+
+    /// Reads more base64 with `read` and decodes it.
+    /// Must be called only when everything decoded before is read.
+    pub fn poll_fill(&mut self, read: impl FnOnce(&mut [u8]) -> Poll<io::Result<usize>>) -> Poll<io::Result<()>> {
+        debug_assert!(self.decoded().is_empty());
+
+        let start = self.input.len();
+        self.input.resize(start + READ_SIZE, 0);
+        let result = read(&mut self.input[start..]);
+        let len = match &result {
+            Poll::Ready(Ok(len)) => *len,
+            _ => 0,
+        };
+        self.input.truncate(start + len);
+
+        ready!(result)?;
+        if len == 0 {
+            self.eof = true;
+        }
+        Poll::Ready(self.decode())
+    }
+
+    fn decode(&mut self) -> io::Result<()> {
+        self.output.clear();
+        self.read = 0;
+
+        // The last group is kept until the end because it may have padding,
+        // before that '=' is an invalid byte
+        let (len, config) = match self.eof {
+            true => (self.input.len(), self.config),
+            false => (self.input.len().saturating_sub(1) / 4 * 4, Config { padding: false, ..self.config }),
+        };
+
+        base64_simd::decode_with::<LANES>(&self.input[..len], &mut self.output, config)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err.add_offset(self.offset)))?;
+
+        self.input.drain(..len);
+        self.offset += len;
+        Ok(())
+    }
+}
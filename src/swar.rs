@@ -0,0 +1,232 @@
+//! Encoding and decoding in `u64` words, for targets without SIMD.
+//!
+//! Encoding takes 6 bytes to 8 chars per step, decoding looks up every char
+//! in one of 4 tables which have the decoded bits already in place, as in
+//! Chromium's modp_b64, so 8 chars to 6 bytes are only loads and ORs.
+//! Whole words are written, the output has room for the extra bytes.
+
+use crate::base64_simd::{decoded_len, encoded_len, padded_encoded_len};
+use crate::common;
+use crate::{Alphabet, Config, DecodeError};
+
+// Bit 24 is set only in the entries of chars which are not in the alphabet
+const INVALID: u32 = 0x01ff_ffff;
+
+static STANDARD_TABLES: [[u32; 256]; 4] = decode_tables(Alphabet::Standard.chars());
+static URL_SAFE_TABLES: [[u32; 256]; 4] = decode_tables(Alphabet::UrlSafe.chars());
+
+// For every position in 4 chars, the sextet of a char at its bits of
+// the 3 decoded bytes, in a little endian u32:
+// 00aaaaaa 00bbbbbb 00cccccc 00dddddd
+// => aaaaaabb bbbbcccc ccdddddd
+const fn decode_tables(chars: &[u8; 64]) -> [[u32; 256]; 4] {
+    let mut tables = [[INVALID; 256]; 4];
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i] as usize;
+        let s = i as u32;
+        tables[0][c] = s << 2;
+        tables[1][c] = (s >> 4) | ((s & 0x0f) << 12);
+        tables[2][c] = ((s >> 2) << 8) | ((s & 0x03) << 22);
+        tables[3][c] = s << 16;
+        i += 1;
+    }
+    tables
+}
+
+fn tables(alphabet: Alphabet) -> &'static [[u32; 256]; 4] {
+    match alphabet {
+        Alphabet::Standard => &STANDARD_TABLES,
+        Alphabet::UrlSafe => &URL_SAFE_TABLES,
+    }
+}
+
+pub fn encode(data: &[u8], out: &mut Vec<u8>) {
+    encode_with(data, out, Config::STANDARD)
+}
+
+pub fn encode_with(data: &[u8], out: &mut Vec<u8>, config: Config) {
+    let final_size = match config.padding {
+        true => padded_encoded_len(data.len()),
+        false => encoded_len(data.len()),
+    };
+    out.reserve(final_size);
+
+    let chars = config.alphabet.chars();
+    let mut ptr = out.as_mut_ptr_range().end;
+    let start = ptr;
+    let mut input = data;
+
+    // main loop, only the first 6 bytes of a loaded word are encoded
+    while input.len() >= 8 {
+        let word = u64::from_be_bytes(input[..8].try_into().unwrap());
+        // Safety: 8 chars for every 6 bytes of the input were reserved
+        unsafe {
+            ptr.cast::<[u8; 8]>().write_unaligned(encode_hot(word, chars));
+            ptr = ptr.add(8);
+        }
+        input = &input[6..];
+    }
+
+    // remainder, the word is filled with zeros
+    for chunk in input.chunks(6) {
+        let mut bytes = [0u8; 8];
+        bytes[..chunk.len()].copy_from_slice(chunk);
+        let asciis = encode_hot(u64::from_be_bytes(bytes), chars);
+
+        let len = encoded_len(chunk.len());
+        // Safety: see above
+        unsafe {
+            std::ptr::copy_nonoverlapping(asciis.as_ptr(), ptr, len);
+            ptr = ptr.add(len);
+        }
+    }
+
+    // Safety: all bytes up to `ptr` were written
+    unsafe {
+        let len = out.len() + ptr.offset_from(start) as usize;
+        out.set_len(len);
+    }
+
+    // padding
+    if config.padding {
+        common::pad_with_trailing_eq(data.len(), out);
+    }
+}
+
+pub fn decode(data: &[u8], out: &mut Vec<u8>) -> Result<(), DecodeError> {
+    decode_with(data, out, Config::STANDARD)
+}
+
+pub fn decode_with(data: &[u8], out: &mut Vec<u8>, config: Config) -> Result<(), DecodeError> {
+    // without padding '=' is an invalid byte
+    let data = if config.padding { common::remove_trailing_eq(data) } else { data };
+
+    // a whole word is written for every 6 bytes
+    let final_size = decoded_len(data.len());
+    let extra_size = 2;
+    out.reserve(final_size + extra_size);
+
+    let tables = tables(config.alphabet);
+    let mut ptr = out.as_mut_ptr_range().end;
+    let start = ptr;
+    let mut invalid = 0;
+
+    let mut chunks = data.chunks_exact(8);
+
+    // main loop
+    for chunk in &mut chunks {
+        let (word, error) = decode_hot(chunk.try_into().unwrap(), tables);
+        invalid |= error;
+        // Safety: decoded_len() plus the extra bytes were reserved
+        unsafe {
+            ptr.cast::<[u8; 8]>().write_unaligned(word.to_le_bytes());
+            ptr = ptr.add(6);
+        }
+    }
+
+    // remainder, the word is filled with 'A' which is 0
+    let rest = chunks.remainder();
+    if !rest.is_empty() {
+        let mut asciis = [b'A'; 8];
+        asciis[..rest.len()].copy_from_slice(rest);
+        let (word, error) = decode_hot(&asciis, tables);
+        invalid |= error;
+        let len = decoded_len(rest.len());
+        // Safety: see above
+        unsafe {
+            std::ptr::copy_nonoverlapping(word.to_le_bytes().as_ptr(), ptr, len);
+            ptr = ptr.add(len);
+        }
+    }
+
+    if invalid & !0x00ff_ffff != 0 {
+        return Err(common::find_invalid(data, config.alphabet, false));
+    }
+
+    // Safety: all bytes up to `ptr` were written
+    unsafe {
+        let len = out.len() + ptr.offset_from(start) as usize;
+        out.set_len(len);
+    }
+
+    Ok(())
+}
+
+// The 6 high bytes of `word` into 8 chars
+#[inline]
+pub(crate) fn encode_hot(word: u64, chars: &[u8; 64]) -> [u8; 8] {
+    std::array::from_fn(|i| chars[(word >> (58 - 6 * i)) as usize & 0b111111])
+}
+
+// 8 chars into 6 bytes in the low bytes of a little endian word,
+// and the OR of the table entries, bit 24 or above is set for invalid chars
+#[inline]
+pub(crate) fn decode_hot(asciis: &[u8; 8], tables: &[[u32; 256]; 4]) -> (u64, u32) {
+    let lo = tables[0][asciis[0] as usize]
+        | tables[1][asciis[1] as usize]
+        | tables[2][asciis[2] as usize]
+        | tables[3][asciis[3] as usize];
+    let hi = tables[0][asciis[4] as usize]
+        | tables[1][asciis[5] as usize]
+        | tables[2][asciis[6] as usize]
+        | tables[3][asciis[7] as usize];
+
+    (lo as u64 | ((hi as u64) << 24), lo | hi)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::base64;
+
+    #[test]
+    fn test_tables() {
+        for alphabet in [Alphabet::Standard, Alphabet::UrlSafe] {
+            for byte in 0..=255u8 {
+                let (_, invalid) = decode_hot(&[byte; 8], tables(alphabet));
+                assert_eq!(invalid > 0x00ff_ffff, !alphabet.contains(byte), "{byte}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_encode_decode() {
+        let data: Vec<u8> = (0..200u32).map(|i| i.wrapping_mul(2654435761) as u8).collect();
+
+        for config in [Config::STANDARD, Config::STANDARD_NO_PAD, Config::URL_SAFE, Config::URL_SAFE_NO_PAD] {
+            for len in 0..data.len() {
+                let data = &data[..len];
+
+                let mut expected = Vec::new();
+                base64::encode_with(data, &mut expected, config);
+                let mut encoded = b"prefix".to_vec();
+                encode_with(data, &mut encoded, config);
+                assert_eq!(&encoded[6..], expected, "{len}");
+
+                let mut decoded = b"prefix".to_vec();
+                decode_with(&encoded[6..], &mut decoded, config).unwrap();
+                assert_eq!(&decoded[6..], data, "{len}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_errors() {
+        let mut encoded = Vec::new();
+        encode(&[0xa5; 61], &mut encoded);
+        let len = common::remove_trailing_eq(&encoded).len();
+
+        for offset in 0..len {
+            for byte in [b'$', b'=', b'-', 0x80, 0xff] {
+                let mut data = encoded.clone();
+                data[offset] = byte;
+
+                let mut out = b"prefix".to_vec();
+                let err = decode(&data, &mut out).unwrap_err();
+                assert_eq!(err, DecodeError::InvalidByte { offset, byte });
+                assert_eq!(out, b"prefix");
+            }
+        }
+    }
+}
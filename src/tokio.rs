@@ -0,0 +1,202 @@
+//! Base64 encoding writer and decoding reader for tokio.
+
+use std::io;
+use std::pin::Pin;
+use std::task::{ready, Context, Poll};
+
+use ::tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+
+use crate::stream::{Decoder, Encoder};
+use crate::Config;
+
+/// Encodes everything written to it into the inner writer.
+///
+/// Flushing keeps up to 2 bytes which don't make a whole group yet,
+/// they are written with padding on shutdown.
+pub struct EncoderWriter<W> {
+    inner: W,
+    encoder: Encoder,
+}
+
+impl<W: AsyncWrite + Unpin> EncoderWriter<W> {
+    pub fn new(inner: W) -> Self {
+        Self::with_config(inner, Config::STANDARD)
+    }
+
+    pub fn with_config(inner: W, config: Config) -> Self {
+        EncoderWriter { inner, encoder: Encoder::new(config) }
+    }
+
+    pub fn get_ref(&self) -> &W {
+        &self.inner
+    }
+
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+
+    fn poll_write_out(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let inner = &mut self.inner;
+        self.encoder.poll_write_out(|buf| Pin::new(&mut *inner).poll_write(cx, buf))
+    }
+}
+
+impl<W: AsyncWrite + Unpin> AsyncWrite for EncoderWriter<W> {
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        ready!(this.poll_write_out(cx))?;
+        Poll::Ready(this.encoder.encode(buf))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        ready!(this.poll_write_out(cx))?;
+        Pin::new(&mut this.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        this.encoder.finish();
+        ready!(this.poll_write_out(cx))?;
+        Pin::new(&mut this.inner).poll_shutdown(cx)
+    }
+}
+
+/// Decodes base64 read from the inner reader.
+///
+/// Invalid base64 is an `InvalidData` error with [`DecodeError`](crate::DecodeError)
+/// inside, its offset is from the start of the stream.
+pub struct DecoderReader<R> {
+    inner: R,
+    decoder: Decoder,
+}
+
+impl<R: AsyncRead + Unpin> DecoderReader<R> {
+    pub fn new(inner: R) -> Self {
+        Self::with_config(inner, Config::STANDARD)
+    }
+
+    pub fn with_config(inner: R, config: Config) -> Self {
+        DecoderReader { inner, decoder: Decoder::new(config) }
+    }
+
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R: AsyncRead + Unpin> AsyncRead for DecoderReader<R> {
+    fn poll_read(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut ReadBuf<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+
+        while this.decoder.decoded().is_empty() && !this.decoder.is_done() {
+            let inner = &mut this.inner;
+            ready!(this.decoder.poll_fill(|input| {
+                let mut input = ReadBuf::new(input);
+                ready!(Pin::new(&mut *inner).poll_read(cx, &mut input))?;
+                Poll::Ready(Ok(input.filled().len()))
+            }))?;
+        }
+
+        let decoded = this.decoder.decoded();
+        let len = usize::min(decoded.len(), buf.remaining());
+        buf.put_slice(&decoded[..len]);
+        this.decoder.consume(len);
+
+        Poll::Ready(Ok(()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ::tokio::io::{duplex, AsyncReadExt, AsyncWriteExt};
+
+    use super::*;
+    use crate::common::test_data;
+    use crate::{base64_simd, DecodeError};
+
+    #[tokio::test]
+    async fn test_encoder() {
+        for len in [0, 1, 2, 3, 100, 20_000] {
+            let data = test_data(len);
+            let (client, mut server) = duplex(64);
+
+            let writer = ::tokio::spawn({
+                let data = data.clone();
+                async move {
+                    let mut writer = EncoderWriter::new(client);
+                    // odd pieces, so groups are split between writes
+                    for piece in data.chunks(7) {
+                        writer.write_all(piece).await.unwrap();
+                    }
+                    writer.shutdown().await.unwrap();
+                }
+            });
+
+            let mut encoded = Vec::new();
+            server.read_to_end(&mut encoded).await.unwrap();
+            writer.await.unwrap();
+
+            assert_eq!(encoded, base64_simd::encode_to_string::<16>(&data).as_bytes());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_flush() {
+        let (client, mut server) = duplex(1024);
+        let mut writer = EncoderWriter::with_config(client, Config::URL_SAFE);
+
+        // flush writes whole groups only, the padding waits for shutdown
+        writer.write_all(b"\xfb\xff\xbf\xfb").await.unwrap();
+        writer.flush().await.unwrap();
+        let mut encoded = [0; 4];
+        server.read_exact(&mut encoded).await.unwrap();
+        assert_eq!(&encoded, b"-_-_");
+
+        writer.shutdown().await.unwrap();
+        let mut encoded = Vec::new();
+        server.read_to_end(&mut encoded).await.unwrap();
+        assert_eq!(encoded, b"-w==");
+
+        assert!(writer.write_all(b"1").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_decoder() {
+        for len in [0, 1, 2, 3, 100, 20_000] {
+            let data = test_data(len);
+            let (mut client, server) = duplex(64);
+
+            let encoded = base64_simd::encode_to_string::<16>(&data);
+            let writer = ::tokio::spawn(async move {
+                client.write_all(encoded.as_bytes()).await.unwrap();
+            });
+
+            let mut decoded = Vec::new();
+            DecoderReader::new(server).read_to_end(&mut decoded).await.unwrap();
+            writer.await.unwrap();
+
+            assert_eq!(decoded, data);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_decoder_errors() {
+        let mut encoded = base64_simd::encode_to_string::<16>(&test_data(20_000)).into_bytes();
+        encoded[10_000] = b'=';
+
+        let err = DecoderReader::new(&encoded[..]).read_to_end(&mut Vec::new()).await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        let err = err.into_inner().unwrap().downcast::<DecodeError>().unwrap();
+        assert_eq!(*err, DecodeError::InvalidByte { offset: 10_000, byte: b'=' });
+
+        let mut reader = DecoderReader::with_config(&b"-_-_-w"[..], Config::URL_SAFE_NO_PAD);
+        let mut decoded = Vec::new();
+        reader.read_to_end(&mut decoded).await.unwrap();
+        assert_eq!(decoded, b"\xfb\xff\xbf\xfb");
+    }
+}
@@ -0,0 +1,193 @@
+//! WebSocket opening handshake keys (RFC 6455, section 4).
+
+use std::fmt;
+
+use crate::base64_simd;
+use crate::DecodeError;
+
+// Appended to `Sec-WebSocket-Key` before hashing
+const GUID: &[u8] = b"258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+
+pub const KEY_LEN: usize = base64_simd::padded_encoded_len(16);
+pub const ACCEPT_LEN: usize = base64_simd::padded_encoded_len(20);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Error {
+    // A key is base64 of a 16-byte nonce, so it has 24 chars
+    InvalidKeyLength(usize),
+    InvalidKey(DecodeError),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::InvalidKeyLength(len) => write!(f, "key has {len} chars instead of {KEY_LEN}"),
+            Error::InvalidKey(err) => write!(f, "{err}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::InvalidKey(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+/// Builds `Sec-WebSocket-Key` of a client from a random nonce.
+pub fn key(nonce: &[u8; 16]) -> [u8; KEY_LEN] {
+    base64_simd::encode_array(nonce)
+}
+
+/// Checks that `Sec-WebSocket-Key` from a client is base64 of 16 bytes
+/// and returns them.
+pub fn validate_key(key: &str) -> Result<[u8; 16], Error> {
+    let key: &[u8; KEY_LEN] = key
+        .trim()
+        .as_bytes()
+        .try_into()
+        .map_err(|_| Error::InvalidKeyLength(key.trim().len()))?;
+
+    base64_simd::decode_array(key).map_err(Error::InvalidKey)
+}
+
+/// Computes `Sec-WebSocket-Accept` for the `Sec-WebSocket-Key` of a client:
+/// base64 of SHA-1 of the key and the GUID.
+pub fn accept(key: &str) -> [u8; ACCEPT_LEN] {
+    let mut sha1 = Sha1::new();
+    sha1.update(key.trim().as_bytes());
+    sha1.update(GUID);
+
+    base64_simd::encode_array(&sha1.finish())
+}
+
+// Minimal SHA-1 (RFC 3174), only for the handshake.
+// SHA-1 is broken for signatures, but it is what RFC 6455 uses.
+struct Sha1 {
+    state: [u32; 5],
+    block: [u8; 64],
+    block_len: usize,
+    len: u64,
+}
+
+impl Sha1 {
+    fn new() -> Self {
+        Sha1 {
+            state: [0x67452301, 0xEFCDAB89, 0x98BADCFE, 0x10325476, 0xC3D2E1F0],
+            block: [0; 64],
+            block_len: 0,
+            len: 0,
+        }
+    }
+
+    fn update(&mut self, mut data: &[u8]) {
+        self.len += data.len() as u64;
+
+        while !data.is_empty() {
+            let n = usize::min(64 - self.block_len, data.len());
+            self.block[self.block_len..self.block_len + n].copy_from_slice(&data[..n]);
+            self.block_len += n;
+            data = &data[n..];
+
+            if self.block_len == 64 {
+                self.compress();
+                self.block_len = 0;
+            }
+        }
+    }
+
+    fn finish(mut self) -> [u8; 20] {
+        let bit_len = self.len * 8;
+
+        self.update(&[0x80]);
+        while self.block_len != 56 {
+            self.update(&[0]);
+        }
+        self.update(&bit_len.to_be_bytes());
+
+        let mut digest = [0; 20];
+        for (bytes, word) in digest.chunks_exact_mut(4).zip(self.state) {
+            bytes.copy_from_slice(&word.to_be_bytes());
+        }
+        digest
+    }
+
+    fn compress(&mut self) {
+        let mut w = [0u32; 80];
+        for (i, bytes) in self.block.chunks_exact(4).enumerate() {
+            w[i] = u32::from_be_bytes(bytes.try_into().unwrap());
+        }
+        for i in 16..80 {
+            w[i] = (w[i - 3] ^ w[i - 8] ^ w[i - 14] ^ w[i - 16]).rotate_left(1);
+        }
+
+        let [mut a, mut b, mut c, mut d, mut e] = self.state;
+        for (i, &word) in w.iter().enumerate() {
+            let (f, k) = match i {
+                0..=19 => ((b & c) | (!b & d), 0x5A827999),
+                20..=39 => (b ^ c ^ d, 0x6ED9EBA1),
+                40..=59 => ((b & c) | (b & d) | (c & d), 0x8F1BBCDC),
+                _ => (b ^ c ^ d, 0xCA62C1D6),
+            };
+
+            let temp = a.rotate_left(5)
+                .wrapping_add(f)
+                .wrapping_add(e)
+                .wrapping_add(k)
+                .wrapping_add(word);
+            e = d;
+            d = c;
+            c = b.rotate_left(30);
+            b = a;
+            a = temp;
+        }
+
+        for (state, value) in self.state.iter_mut().zip([a, b, c, d, e]) {
+            *state = state.wrapping_add(value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sha1() {
+        let mut sha1 = Sha1::new();
+        sha1.update(b"abc");
+        assert_eq!(sha1.finish(), [
+            0xa9, 0x99, 0x3e, 0x36, 0x47, 0x06, 0x81, 0x6a, 0xba, 0x3e,
+            0x25, 0x71, 0x78, 0x50, 0xc2, 0x6c, 0x9c, 0xd0, 0xd8, 0x9d,
+        ]);
+
+        // more than one block, split between updates
+        let mut sha1 = Sha1::new();
+        sha1.update(b"abcdbcdecdefdefgefghfghighij");
+        sha1.update(b"hijkijkljklmklmnlmnomnopnopq");
+        assert_eq!(sha1.finish(), [
+            0x84, 0x98, 0x3e, 0x44, 0x1c, 0x3b, 0xd2, 0x6e, 0xba, 0xae,
+            0x4a, 0xa1, 0xf9, 0x51, 0x29, 0xe5, 0xe5, 0x46, 0x70, 0xf1,
+        ]);
+    }
+
+    #[test]
+    fn test_handshake() {
+        // RFC 6455, section 1.3
+        let key = "dGhlIHNhbXBsZSBub25jZQ==";
+        assert_eq!(&validate_key(key).unwrap(), b"the sample nonce");
+        assert_eq!(&accept(key), b"s3pPLMBiTxaQ9kYGzzhZRbK+xOo=");
+        assert_eq!(&super::key(b"the sample nonce"), key.as_bytes());
+    }
+
+    #[test]
+    fn test_invalid_key() {
+        assert_eq!(validate_key("dGhlIHNhbXBsZSBub25jZQ"), Err(Error::InvalidKeyLength(22)));
+        assert_eq!(
+            validate_key("dGhlIHNhbXBsZSBub25j$Q=="),
+            Err(Error::InvalidKey(DecodeError::InvalidByte { offset: 20, byte: b'$' }))
+        );
+    }
+}